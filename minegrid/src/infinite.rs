@@ -0,0 +1,341 @@
+//! An endless board, generated in chunks as it is explored.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use rand::prng::XorShiftRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{CellState, GridState};
+
+/// Width and height of a chunk, in cells.
+pub const CHUNK_SIZE: i64 = 16;
+
+/// Mines in each chunk, about the density of a medium board.
+pub const CHUNK_MINES: usize = 40;
+
+/// Where mines are in one chunk, row by row.
+struct Chunk {
+    mines: Vec<bool>,
+}
+
+/// A cell of an `InfiniteGrid`, at world coordinates that may be negative.
+#[derive(Clone)]
+pub struct InfiniteCell {
+    x: i64,
+    y: i64,
+    mines: u8,
+    state: CellState,
+    surrounding_mines: u8,
+}
+
+impl InfiniteCell {
+    pub fn x(&self) -> i64 {
+        self.x
+    }
+
+    pub fn y(&self) -> i64 {
+        self.y
+    }
+
+    pub fn mines(&self) -> u8 {
+        self.mines
+    }
+
+    pub fn state(&self) -> CellState {
+        self.state
+    }
+
+    pub fn surrounding_mines(&self) -> u8 {
+        self.surrounding_mines
+    }
+}
+
+/// A board with no edges. Mines are placed a chunk at a time from the seed,
+/// so the same seed always gives the same board, and only cells the player
+/// has changed are stored. The cells around (0, 0) never hold mines, giving a
+/// safe place to start.
+pub struct InfiniteGrid {
+    seed: u64,
+    chunks: RefCell<HashMap<(i64, i64), Chunk>>,
+    states: HashMap<(i64, i64), CellState>,
+    question_marks: bool,
+    mines_flagged: u32,
+    score: u32,
+    state: GridState,
+}
+
+impl InfiniteGrid {
+    pub fn new(seed: u64) -> InfiniteGrid {
+        InfiniteGrid {
+            seed,
+            chunks: RefCell::new(HashMap::new()),
+            states: HashMap::new(),
+            question_marks: false,
+            mines_flagged: 0,
+            score: 0,
+            state: GridState::Play,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Never `GridState::Win`: the board goes on forever.
+    pub fn state(&self) -> GridState {
+        self.state
+    }
+
+    /// Number of safe cells revealed.
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn mines_flagged(&self) -> u32 {
+        self.mines_flagged
+    }
+
+    /// Number of chunks generated so far.
+    pub fn chunks(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+
+    pub fn question_marks(&self) -> bool {
+        self.question_marks
+    }
+
+    /// Enables or disables "?" marks in the flag cycle. Disabling clears any
+    /// existing marks.
+    pub fn set_question_marks(&mut self, enabled: bool) {
+        self.question_marks = enabled;
+        if !enabled {
+            self.states.retain(|_, state| *state != CellState::Question);
+        }
+    }
+
+    pub fn get_cell(&self, x: i64, y: i64) -> InfiniteCell {
+        InfiniteCell {
+            x,
+            y,
+            mines: self.is_mine(x, y) as u8,
+            state: self.cell_state(x, y),
+            surrounding_mines: self.count_surrounding_mines(x, y),
+        }
+    }
+
+    fn cell_state(&self, x: i64, y: i64) -> CellState {
+        self.states.get(&(x, y)).cloned().unwrap_or(CellState::Hidden(0))
+    }
+
+    fn set_state(&mut self, x: i64, y: i64, state: CellState) {
+        if let CellState::Hidden(flags) = self.cell_state(x, y) {
+            self.mines_flagged -= flags as u32;
+        }
+        if let CellState::Hidden(flags) = state {
+            self.mines_flagged += flags as u32;
+        }
+
+        if state == CellState::Hidden(0) {
+            self.states.remove(&(x, y));
+        } else {
+            self.states.insert((x, y), state);
+        }
+    }
+
+    fn is_mine(&self, x: i64, y: i64) -> bool {
+        let (cx, cy) = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let mut chunks = self.chunks.borrow_mut();
+        let chunk = chunks.entry((cx, cy))
+            .or_insert_with(|| generate_chunk(self.seed, cx, cy));
+        let (i, j) = (x.rem_euclid(CHUNK_SIZE), y.rem_euclid(CHUNK_SIZE));
+        chunk.mines[(j * CHUNK_SIZE + i) as usize]
+    }
+
+    fn get_neighbors(&self, x: i64, y: i64) -> Vec<(i64, i64)> {
+        let mut neighbors = Vec::with_capacity(8);
+        for j in -1..2 {
+            for i in -1..2 {
+                if (i, j) != (0, 0) {
+                    neighbors.push((x + i, y + j));
+                }
+            }
+        }
+        neighbors
+    }
+
+    fn count_surrounding_mines(&self, x: i64, y: i64) -> u8 {
+        self.get_neighbors(x, y).into_iter()
+            .filter(|&(nx, ny)| self.is_mine(nx, ny))
+            .count() as u8
+    }
+
+    fn count_surrounding_flags(&self, x: i64, y: i64) -> u8 {
+        self.get_neighbors(x, y).into_iter()
+            .map(|(nx, ny)| match self.cell_state(nx, ny) {
+                CellState::Hidden(flags) => flags,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn toggle_flag(&mut self, x: i64, y: i64) {
+        let state = match self.cell_state(x, y) {
+            CellState::Hidden(0) => CellState::Hidden(1),
+            CellState::Hidden(_) if self.question_marks => CellState::Question,
+            CellState::Hidden(_) | CellState::Question => CellState::Hidden(0),
+            CellState::Revealed => return,
+        };
+        self.set_state(x, y, state);
+    }
+
+    pub fn reveal(&mut self, x: i64, y: i64) {
+        if self.state != GridState::Play {
+            return;
+        }
+
+        match self.cell_state(x, y) {
+            CellState::Hidden(0) | CellState::Question => self.open(x, y),
+            CellState::Hidden(_) => {
+                // Do nothing, since players can't reveal flagged cells.
+            },
+            CellState::Revealed => {
+                let surrounding_mines = self.count_surrounding_mines(x, y);
+                if surrounding_mines == 0 || self.count_surrounding_flags(x, y) != surrounding_mines {
+                    return;
+                }
+                for (nx, ny) in self.get_neighbors(x, y) {
+                    self.open(nx, ny);
+                }
+            },
+        }
+    }
+
+    /// Reveals a cell and flood fills from it. Uses a stack rather than
+    /// recursion, since empty regions can be large.
+    fn open(&mut self, x: i64, y: i64) {
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if self.state != GridState::Play {
+                return;
+            }
+            match self.cell_state(x, y) {
+                CellState::Hidden(0) | CellState::Question => {},
+                _ => continue,
+            }
+
+            self.set_state(x, y, CellState::Revealed);
+            if self.is_mine(x, y) {
+                self.state = GridState::Lose;
+                return;
+            }
+
+            self.score += 1;
+            if self.count_surrounding_mines(x, y) == 0 {
+                stack.extend(self.get_neighbors(x, y));
+            }
+        }
+    }
+}
+
+/// Places a chunk's mines using a generator seeded from the board's seed and
+/// the chunk's position.
+fn generate_chunk(seed: u64, cx: i64, cy: i64) -> Chunk {
+    let mut rng = XorShiftRng::seed_from_u64(mix(mix(seed ^ cx as u64) ^ cy as u64));
+    let mut mines = vec![false; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+    let mut placed = 0;
+    while placed < CHUNK_MINES {
+        let (i, j) = (rng.gen_range(0, CHUNK_SIZE), rng.gen_range(0, CHUNK_SIZE));
+        let (x, y) = (cx * CHUNK_SIZE + i, cy * CHUNK_SIZE + j);
+        let index = (j * CHUNK_SIZE + i) as usize;
+        // Keep the start clear.
+        if mines[index] || (x.abs() <= 1 && y.abs() <= 1) {
+            continue;
+        }
+        mines[index] = true;
+        placed += 1;
+    }
+    Chunk { mines }
+}
+
+/// The SplitMix64 finalizer, to spread nearby chunk positions far apart.
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod infinite_test {
+    use super::*;
+
+    fn mines_in(grid: &InfiniteGrid, cx: i64, cy: i64) -> usize {
+        let mut count = 0;
+        for j in 0..CHUNK_SIZE {
+            for i in 0..CHUNK_SIZE {
+                count += grid.is_mine(cx * CHUNK_SIZE + i, cy * CHUNK_SIZE + j) as usize;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let (a, b, c) = (InfiniteGrid::new(7), InfiniteGrid::new(7), InfiniteGrid::new(8));
+
+        let layout = |grid: &InfiniteGrid| -> Vec<u8> {
+            (-40..40).map(|i| grid.get_cell(i * 3, -i * 5).mines()).collect()
+        };
+        assert_eq!(layout(&a), layout(&b));
+        assert!(layout(&a) != layout(&c));
+    }
+
+    #[test]
+    fn test_chunks() {
+        let grid = InfiniteGrid::new(1);
+        assert_eq!(0, grid.chunks());
+
+        assert_eq!(CHUNK_MINES, mines_in(&grid, 3, -2));
+        assert_eq!(CHUNK_MINES, mines_in(&grid, -1, -1));
+        assert_eq!(2, grid.chunks());
+    }
+
+    #[test]
+    fn test_safe_start() {
+        for seed in 0..20 {
+            let mut grid = InfiniteGrid::new(seed);
+            grid.reveal(0, 0);
+            assert_eq!(GridState::Play, grid.state());
+            assert!(grid.score() >= 1);
+            assert_eq!(0, grid.get_cell(0, 0).surrounding_mines());
+        }
+    }
+
+    #[test]
+    fn test_get_cell() {
+        let grid = InfiniteGrid::new(5);
+
+        let cell = grid.get_cell(-20, 35);
+        assert_eq!((-20, 35), (cell.x(), cell.y()));
+        assert_eq!(grid.is_mine(-20, 35) as u8, cell.mines());
+        assert_eq!(CellState::Hidden(0), cell.state());
+    }
+
+    #[test]
+    fn test_reveal_mine() {
+        let mut grid = InfiniteGrid::new(3);
+        let mine = (2..).find(|&x| grid.get_cell(x, 0).mines() != 0).unwrap();
+
+        grid.toggle_flag(mine, 0);
+        assert_eq!(1, grid.mines_flagged());
+        grid.reveal(mine, 0);
+        assert_eq!(GridState::Play, grid.state());
+
+        grid.toggle_flag(mine, 0);
+        assert_eq!(0, grid.mines_flagged());
+        grid.reveal(mine, 0);
+        assert_eq!(GridState::Lose, grid.state());
+        assert_eq!(CellState::Revealed, grid.get_cell(mine, 0).state());
+    }
+}
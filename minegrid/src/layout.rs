@@ -0,0 +1,182 @@
+//! Boards written out by hand as text, one row per line:
+//!
+//! * `.` is a hidden safe cell and `*` a hidden mine.
+//! * `F` is a flagged mine and `X` a flag on a safe cell.
+//! * `0` to `9` is a revealed safe cell showing that many surrounding mines,
+//!   and `o` a revealed safe cell with whatever number it gets.
+//! * A space is a hole. Short lines are padded with holes.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::mask::Mask;
+use crate::{CellState, MineGrid, MineGridBuilder};
+
+#[derive(Debug)]
+pub enum LayoutError {
+    Io(io::Error),
+    /// A character that isn't part of the format, at (x, y).
+    UnknownChar(u32, u32, char),
+    /// A revealed number at (x, y) that doesn't match the mines around it.
+    WrongNumber(u32, u32),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LayoutError::Io(ref e) => write!(f, "{}", e),
+            LayoutError::UnknownChar(x, y, ch) => write!(f, "unknown cell {:?} at ({}, {})", ch, x, y),
+            LayoutError::WrongNumber(x, y) => write!(f, "wrong number at ({}, {})", x, y),
+        }
+    }
+}
+
+impl error::Error for LayoutError {}
+
+impl From<io::Error> for LayoutError {
+    fn from(e: io::Error) -> LayoutError {
+        LayoutError::Io(e)
+    }
+}
+
+pub fn parse(text: &str) -> Result<MineGrid, LayoutError> {
+    let rows: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+    let mut mask = Mask::new(width, rows.len() as u32);
+    let mut mine_points = Vec::new();
+    let mut revealed_points = Vec::new();
+
+    for (y, row) in rows.iter().enumerate() {
+        for x in 0..width as usize {
+            let (px, py) = (x as u32, y as u32);
+            match row.get(x).cloned().unwrap_or(' ') {
+                ' ' => mask.set(px, py, false),
+                '*' | 'F' => mine_points.push((px, py)),
+                'o' | '0'..='9' => revealed_points.push((px, py)),
+                '.' | 'X' => {},
+                ch => return Err(LayoutError::UnknownChar(px, py, ch)),
+            }
+        }
+    }
+
+    let mut grid = MineGridBuilder::new(0, 0, 0)
+        .mask(mask)
+        .mine_points(&mine_points)
+        .revealed_points(&revealed_points)
+        .build();
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &ch) in row.iter().enumerate() {
+            let (x, y) = (x as u32, y as u32);
+            match ch {
+                'F' | 'X' => grid.toggle_flag(x, y),
+                '0'..='9' if ch.to_digit(10) != Some(grid.cell(x, y, 0).surrounding_mines as u32) => {
+                    return Err(LayoutError::WrongNumber(x, y));
+                },
+                _ => {},
+            }
+        }
+    }
+
+    Ok(grid)
+}
+
+/// Writes out the first layer of a grid as a layout. Cells with several mines
+/// are written as a single mine.
+pub fn write(grid: &MineGrid) -> String {
+    let mut text = String::new();
+    for y in 0..grid.height() {
+        let mut row = String::new();
+        for x in 0..grid.width() {
+            let cell = match grid.get_cell(x, y) {
+                Some(cell) => cell,
+                None => {
+                    row.push(' ');
+                    continue;
+                },
+            };
+            row.push(match (cell.state, cell.mines) {
+                (CellState::Revealed, 0) => std::char::from_digit(cell.surrounding_mines as u32, 10).unwrap_or('o'),
+                (CellState::Hidden(0), 0) | (CellState::Question, 0) => '.',
+                (CellState::Hidden(_), 0) => 'X',
+                (CellState::Hidden(0), _) | (CellState::Question, _) | (CellState::Revealed, _) => '*',
+                (CellState::Hidden(_), _) => 'F',
+            });
+        }
+        text.push_str(row.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<MineGrid, LayoutError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod layout_test {
+    use super::*;
+    use crate::GridState;
+
+    #[test]
+    fn test_parse() {
+        let grid = parse("*..\n.. .\n..*").unwrap();
+
+        assert_eq!(4, grid.width());
+        assert_eq!(3, grid.height());
+        assert_eq!(2, grid.mines());
+        assert_eq!(1, grid.get_cell(0, 0).unwrap().mines());
+        assert_eq!(1, grid.get_cell(2, 2).unwrap().mines());
+        assert!(grid.get_cell(2, 1).is_none());
+        assert!(grid.get_cell(3, 0).is_none());
+        assert!(grid.get_cell(3, 1).is_some());
+        assert_eq!(CellState::Hidden(0), grid.get_cell(1, 1).unwrap().state());
+    }
+
+    #[test]
+    fn test_starting_state() {
+        let mut grid = parse("F1.\n11.\nX..").unwrap();
+
+        assert_eq!(GridState::Play, grid.state());
+        assert_eq!(-1, grid.mines_left());
+        assert_eq!(CellState::Hidden(1), grid.get_cell(0, 0).unwrap().state());
+        assert_eq!(CellState::Hidden(1), grid.get_cell(0, 2).unwrap().state());
+        assert_eq!(CellState::Revealed, grid.get_cell(1, 1).unwrap().state());
+        assert_eq!(CellState::Hidden(0), grid.get_cell(2, 2).unwrap().state());
+
+        // Pre-revealed cells count towards winning.
+        grid.reveal(2, 2);
+        assert_eq!(GridState::Play, grid.state());
+        grid.toggle_flag(0, 2);
+        grid.reveal(0, 2);
+        assert_eq!(GridState::Win, grid.state());
+
+        let grid = parse("*oo").unwrap();
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_write() {
+        let text = "F1..\n11 .\n X.*\n";
+        assert_eq!(text, write(&parse(text).unwrap()));
+
+        let mut grid = MineGrid::with_mines(4, 2, &[(3, 1)]);
+        grid.reveal(0, 0);
+        assert_eq!("001.\n001*\n", write(&grid));
+    }
+
+    #[test]
+    fn test_errors() {
+        match parse("..\n.#") {
+            Err(LayoutError::UnknownChar(1, 1, '#')) => {},
+            other => panic!("expected an unknown char, got {:?}", other.err()),
+        }
+        match parse("*2.") {
+            Err(LayoutError::WrongNumber(1, 0)) => {},
+            other => panic!("expected a wrong number, got {:?}", other.err()),
+        }
+    }
+}
@@ -1,10 +1,24 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use rand::Rng;
+use rand::prng::XorShiftRng;
+use rand::{Rng, SeedableRng};
+
+pub mod infinite;
+pub mod layout;
+pub mod mask;
+pub mod neighborhood;
+pub mod solver;
+
+use layout::LayoutError;
+use mask::Mask;
+use neighborhood::Neighborhood;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CellState {
     Hidden(u8),
+    /// Hidden and marked with a "?". Counts as unflagged.
+    Question,
     Revealed,
 }
 
@@ -12,11 +26,47 @@ pub enum CellState {
 pub struct Cell {
     x: u32,
     y: u32,
+    z: u32,
     mines: u8,
     state: CellState,
     surrounding_mines: u8,
 }
 
+/// How the edges of the grid connect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Topology {
+    /// Cells on the edges have fewer neighbors.
+    Bounded,
+    /// Neighbors wrap around to the opposite edge, and the top layer to the
+    /// bottom one.
+    Torus,
+}
+
+/// The shape of the cells, and so which cells neighbor each other unless a
+/// `Neighborhood` is given explicitly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tiling {
+    /// Square cells with 8 neighbors.
+    Square,
+    /// Hexagonal cells with 6 neighbors, in "odd-r" offset coordinates: odd
+    /// rows are shifted half a cell to the right. On a torus, use an even
+    /// height so rows keep alternating across the wrap.
+    Hex,
+}
+
+/// How a cell should be shown once the game is lost.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EndView {
+    /// Shown as it was during play.
+    Unchanged,
+    /// A mine the player didn't flag.
+    Mine,
+    /// The mine whose reveal lost the game.
+    Exploded,
+    /// Flagged with more flags than it has mines.
+    WrongFlag,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum GridState {
     Play,
@@ -28,15 +78,52 @@ pub struct MineGrid {
     cells: Vec<Vec<Cell>>, // TODO: Use a single vector that you index into.
     width: u32,
     height: u32,
+    depth: u32,
+    mask: Mask,
     mines: u32,
     max_mines: u8,
+    topology: Topology,
+    tiling: Tiling,
+    neighborhood: Arc<dyn Neighborhood + Send + Sync>,
     mines_flagged: u32,
+    question_marks: bool,
     spaces_left: u32,
     state: GridState,
-    //seed: u64,
+    exploded: Option<(u32, u32, u32)>,
+    seed: Option<u64>,
+}
+
+/// Configures how a random `MineGrid` is generated.
+#[derive(Clone)]
+pub struct MineGridBuilder {
+    width: u32,
+    height: u32,
+    depth: u32,
+    mask: Option<Mask>,
+    mines: u32,
+    max_mines: u8,
+    topology: Topology,
+    tiling: Tiling,
+    neighborhood: Option<Arc<dyn Neighborhood + Send + Sync>>,
+    mine_points: Option<Vec<(u32, u32, u32)>>,
+    revealed_points: Vec<(u32, u32)>,
+    seed: Option<u64>,
 }
 
 impl Cell {
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// The cell's layer.
+    pub fn z(&self) -> u32 {
+        self.z
+    }
+
     pub fn mines(&self) -> u8 {
         self.mines
     }
@@ -50,57 +137,275 @@ impl Cell {
     }
 }
 
-impl MineGrid {
-    pub fn new(width: u32, height: u32, mines: u32) -> MineGrid {
-        let mut cells = Vec::with_capacity(height as usize);
+impl MineGridBuilder {
+    pub fn new(width: u32, height: u32, mines: u32) -> MineGridBuilder {
+        MineGridBuilder {
+            width,
+            height,
+            depth: 1,
+            mask: None,
+            mines,
+            max_mines: 1,
+            topology: Topology::Bounded,
+            tiling: Tiling::Square,
+            neighborhood: None,
+            mine_points: None,
+            revealed_points: Vec::new(),
+            seed: None,
+        }
+    }
+
+    /// Stacks `depth` layers of the grid on top of each other. Each cell also
+    /// neighbors the cells above and below it and their neighbors, for 26 in
+    /// all on square tiles.
+    pub fn depth(mut self, depth: u32) -> MineGridBuilder {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Cuts holes in the grid, taking its width and height from the mask. On a
+    /// 3D grid, every layer has the same shape.
+    pub fn mask(mut self, mask: Mask) -> MineGridBuilder {
+        self.width = mask.width();
+        self.height = mask.height();
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Allows up to `max_mines` mines to be placed in a single cell.
+    pub fn max_mines(mut self, max_mines: u8) -> MineGridBuilder {
+        self.max_mines = max_mines.max(1);
+        self
+    }
+
+    pub fn topology(mut self, topology: Topology) -> MineGridBuilder {
+        self.topology = topology;
+        self
+    }
+
+    pub fn tiling(mut self, tiling: Tiling) -> MineGridBuilder {
+        self.tiling = tiling;
+        self
+    }
+
+    /// Overrides which cells count as neighbors, instead of the tiling's own.
+    pub fn neighborhood<N: Neighborhood + Send + Sync + 'static>(mut self, neighborhood: N) -> MineGridBuilder {
+        self.neighborhood = Some(Arc::new(neighborhood));
+        self
+    }
+
+    /// Places mines at exactly these points instead of randomly, ignoring the
+    /// mine count. Repeating a point stacks another mine in it. Points in holes
+    /// are ignored.
+    pub fn mine_points(mut self, mine_points: &[(u32, u32)]) -> MineGridBuilder {
+        self.mine_points = Some(mine_points.iter().map(|&(x, y)| (x, y, 0)).collect());
+        self
+    }
 
-        // Randomly place mines
-        let mut rng = rand::thread_rng();
-        let mut mine_points = HashSet::new();
-        while mine_points.len() != mines as usize {
+    /// Starts with these cells of the first layer already revealed, without
+    /// flood filling from them. Points that are holes or hold mines are
+    /// ignored.
+    pub fn revealed_points(mut self, revealed_points: &[(u32, u32)]) -> MineGridBuilder {
+        self.revealed_points = revealed_points.to_vec();
+        self
+    }
+
+    /// Like `mine_points`, with a layer for each point.
+    pub fn mine_points_at(mut self, mine_points: &[(u32, u32, u32)]) -> MineGridBuilder {
+        self.mine_points = Some(mine_points.to_vec());
+        self
+    }
+
+    /// Places the mines from `seed`, so the same settings and seed always give
+    /// the same grid. Without one, a random seed is picked.
+    pub fn seed(mut self, seed: u64) -> MineGridBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(self) -> MineGrid {
+        if let Some(ref mine_points) = self.mine_points {
+            return self.build_with_points(mine_points);
+        }
+
+        let (width, height, depth, max_mines) = (self.width, self.height, self.depth, self.max_mines);
+        let mask = self.build_mask();
+        assert!(self.mines as u64 <= mask.count() as u64 * depth as u64 * max_mines as u64,
+                "Cannot fit {} mines in a {}x{}x{} grid!", self.mines, width, height, depth);
+
+        // Randomly place mines, stacking up to max_mines in a cell.
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        let mut mine_counts = HashMap::new();
+        let mut mine_points = Vec::with_capacity(self.mines as usize);
+        while mine_points.len() != self.mines as usize {
             let point = (rng.gen_range(0, width),
-                         rng.gen_range(0, height));
-            mine_points.insert(point);
+                         rng.gen_range(0, height),
+                         rng.gen_range(0, depth));
+            if !mask.contains(point.0, point.1) {
+                continue;
+            }
+            let count = mine_counts.entry(point).or_insert(0);
+            if *count < max_mines {
+                *count += 1;
+                mine_points.push(point);
+            }
         }
 
-        for j in 0..height {
-            let mut row = Vec::with_capacity(width as usize);
-            for i in 0..width {
-                row.push(Cell {
-                    x: i,
-                    y: j,
-                    mines: if mine_points.contains(&(i, j)) { 1 } else { 0 },
-                    state: CellState::Hidden(0),
-                    surrounding_mines: 0,
-                });
+        let mut grid = self.build_with_points(&mine_points);
+        grid.seed = Some(seed);
+        grid
+    }
+
+    fn build_mask(&self) -> Mask {
+        match self.mask {
+            Some(ref mask) => mask.clone(),
+            None => Mask::new(self.width, self.height),
+        }
+    }
+
+    fn build_with_points(&self, mine_points: &[(u32, u32, u32)]) -> MineGrid {
+        let (width, height, depth) = (self.width, self.height, self.depth);
+        let mask = self.build_mask();
+        let mut mine_counts = HashMap::new();
+        let mut total_mines = 0;
+        for &(x, y, z) in mine_points {
+            if mask.contains(x, y) && z < depth {
+                // A cell holds at most u8::MAX mines; drop any beyond that.
+                let count = mine_counts.entry((x, y, z)).or_insert(0u8);
+                if *count < u8::MAX {
+                    *count += 1;
+                    total_mines += 1;
+                }
+            }
+        }
+
+        // Layers are stored one after another, as rows z * height + y.
+        let mut cells = Vec::with_capacity((height * depth) as usize);
+        let mut max_mines = self.max_mines;
+        let mut spaces = 0;
+
+        for k in 0..depth {
+            for j in 0..height {
+                let mut row = Vec::with_capacity(width as usize);
+                for i in 0..width {
+                    let mines = mine_counts.get(&(i, j, k)).cloned().unwrap_or(0);
+                    max_mines = max_mines.max(mines);
+                    if mines == 0 && mask.contains(i, j) {
+                        spaces += 1;
+                    }
+                    row.push(Cell {
+                        x: i,
+                        y: j,
+                        z: k,
+                        mines,
+                        state: CellState::Hidden(0),
+                        surrounding_mines: 0,
+                    });
+                }
+                cells.push(row);
             }
-            cells.push(row);
         }
 
         let mut grid = MineGrid {
             cells,
             width,
             height,
-            mines,
-            max_mines: 1,
+            depth,
+            mask,
+            mines: total_mines,
+            max_mines,
+            topology: self.topology,
+            tiling: self.tiling,
+            neighborhood: match self.neighborhood {
+                Some(ref neighborhood) => neighborhood.clone(),
+                None => match self.tiling {
+                    Tiling::Square => Arc::new(neighborhood::Square),
+                    Tiling::Hex => Arc::new(neighborhood::Hex),
+                },
+            },
             mines_flagged: 0,
-            spaces_left: width * height - mines,
+            question_marks: false,
+            spaces_left: spaces,
             state: GridState::Play,
+            exploded: None,
+            seed: None,
         };
 
         // Cache surrounding mine count in each cell.
-        for j in 0..height {
-            for i in 0..width {
-                grid.cells[j as usize][i as usize].surrounding_mines =
-                    grid.count_surrounding_mines(i, j);
+        for k in 0..depth {
+            for j in 0..height {
+                for i in 0..width {
+                    grid.cell_mut(i, j, k).surrounding_mines = grid.count_surrounding_mines(i, j, k);
+                }
             }
         }
 
+        for &(x, y) in &self.revealed_points {
+            if grid.check_point(x, y) && grid.cell(x, y, 0).mines == 0 &&
+                grid.cell(x, y, 0).state != CellState::Revealed {
+                grid.set_state(x, y, 0, CellState::Revealed);
+                grid.spaces_left -= 1;
+            }
+        }
+        if grid.spaces_left == 0 && !self.revealed_points.is_empty() {
+            grid.win();
+        }
+
         grid
     }
+}
+
+impl MineGrid {
+    pub fn new(width: u32, height: u32, mines: u32) -> MineGrid {
+        MineGridBuilder::new(width, height, mines).build()
+    }
+
+    /// Creates a grid with mines at exactly the given points instead of
+    /// placing them randomly. Repeating a point stacks another mine in it.
+    pub fn with_mines(width: u32, height: u32, mine_points: &[(u32, u32)]) -> MineGrid {
+        MineGridBuilder::new(width, height, 0)
+            .mine_points(mine_points)
+            .build()
+    }
+
+    /// Creates a grid from a text layout, as described in `layout`.
+    pub fn from_layout(text: &str) -> Result<MineGrid, LayoutError> {
+        layout::parse(text)
+    }
+
+    pub fn with_seed(width: u32, height: u32, mines: u32, seed: u64) -> MineGrid {
+        MineGridBuilder::new(width, height, mines).seed(seed).build()
+    }
+
+    /// A builder for the same board: its size, settings and mines, placed
+    /// from the seed when there is one, with the revealed cells of the first
+    /// layer opened. Taken before the first move, it builds a fresh copy of
+    /// the board to play again.
+    pub fn to_builder(&self) -> MineGridBuilder {
+        let mut builder = MineGridBuilder::new(self.width, self.height, self.mines)
+            .mask(self.mask.clone())
+            .depth(self.depth)
+            .max_mines(self.max_mines)
+            .topology(self.topology)
+            .tiling(self.tiling);
+        builder.neighborhood = Some(self.neighborhood.clone());
 
-    //pub fn with_seed(width: u32, height: u32, mines: u8) -> MineGrid {
-    //}
+        let cells = self.cells.iter().flatten();
+        builder.revealed_points = cells.clone()
+            .filter(|cell| cell.z == 0 && cell.state == CellState::Revealed)
+            .map(|cell| (cell.x, cell.y))
+            .collect();
+        match self.seed {
+            Some(seed) => builder.seed(seed),
+            None => {
+                let mine_points: Vec<_> = cells
+                    .flat_map(|cell| (0..cell.mines).map(move |_| (cell.x, cell.y, cell.z)))
+                    .collect();
+                builder.mine_points_at(&mine_points)
+            },
+        }
+    }
 
     pub fn width(&self) -> u32 {
         self.width
@@ -110,56 +415,247 @@ impl MineGrid {
         self.height
     }
 
+    /// Number of layers. 1 for a flat grid.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Which points of each layer are cells rather than holes.
+    pub fn mask(&self) -> &Mask {
+        &self.mask
+    }
+
     pub fn state(&self) -> GridState {
         self.state
     }
 
+    /// The seed the mines were placed from. `None` when they were placed by
+    /// hand.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
     pub fn mines(&self) -> u32 {
         self.mines
     }
 
-    pub fn mines_left(&self) -> u32 {
-        self.mines - self.mines_flagged
+    /// The most mines a single cell may hold.
+    pub fn max_mines(&self) -> u8 {
+        self.max_mines
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn tiling(&self) -> Tiling {
+        self.tiling
+    }
+
+    pub fn neighborhood(&self) -> &dyn Neighborhood {
+        &*self.neighborhood
+    }
+
+    /// Mines minus flags placed. Goes negative when over-flagged.
+    pub fn mines_left(&self) -> i32 {
+        self.mines as i32 - self.mines_flagged as i32
+    }
+
+    pub fn question_marks(&self) -> bool {
+        self.question_marks
+    }
+
+    /// Enables or disables "?" marks in the flag cycle. Disabling clears any
+    /// existing marks.
+    pub fn set_question_marks(&mut self, enabled: bool) {
+        self.question_marks = enabled;
+        if !enabled {
+            for cell in self.cells.iter_mut().flat_map(|row| row.iter_mut()) {
+                if cell.state == CellState::Question {
+                    cell.state = CellState::Hidden(0);
+                }
+            }
+        }
     }
 
     pub fn check_point(&self, x: u32, y: u32) -> bool {
-        x < self.width && y < self.height
+        self.check_point_at(x, y, 0)
+    }
+
+    pub fn check_point_at(&self, x: u32, y: u32, z: u32) -> bool {
+        z < self.depth && self.mask.contains(x, y)
+    }
+
+    /// Maps a point that may lie off the grid back onto it: wrapping around on
+    /// a torus, or `None` past the edge of a bounded grid. Holes are `None`.
+    pub fn wrap_point(&self, x: i64, y: i64) -> Option<(u32, u32)> {
+        self.wrap_point_at(x, y, 0).map(|(x, y, _)| (x, y))
+    }
+
+    /// Like `wrap_point`, also wrapping between the top and bottom layers.
+    pub fn wrap_point_at(&self, x: i64, y: i64, z: i64) -> Option<(u32, u32, u32)> {
+        match self.topology {
+            Topology::Bounded => {
+                if x < 0 || y < 0 || z < 0 || !self.check_point_at(x as u32, y as u32, z as u32) {
+                    return None;
+                }
+                Some((x as u32, y as u32, z as u32))
+            },
+            Topology::Torus => {
+                if self.width == 0 || self.height == 0 {
+                    return None;
+                }
+                let (x, y) = (x.rem_euclid(self.width as i64) as u32,
+                              y.rem_euclid(self.height as i64) as u32);
+                if !self.mask.contains(x, y) {
+                    return None;
+                }
+                Some((x, y, z.rem_euclid(self.depth as i64) as u32))
+            },
+        }
+    }
+
+    fn cell(&self, x: u32, y: u32, z: u32) -> &Cell {
+        &self.cells[(z * self.height + y) as usize][x as usize]
+    }
+
+    fn cell_mut(&mut self, x: u32, y: u32, z: u32) -> &mut Cell {
+        &mut self.cells[(z * self.height + y) as usize][x as usize]
     }
 
     pub fn get_cell(&self, x: u32, y: u32) -> Option<Cell> {
-        if self.check_point(x, y) {
-            Some(self.cells[y as usize][x as usize].clone())
+        self.get_cell_at(x, y, 0)
+    }
+
+    pub fn get_cell_at(&self, x: u32, y: u32, z: u32) -> Option<Cell> {
+        if self.check_point_at(x, y, z) {
+            Some(self.cell(x, y, z).clone())
         } else {
             None
         }
     }
 
+    pub fn end_view(&self, x: u32, y: u32) -> Option<EndView> {
+        self.end_view_at(x, y, 0)
+    }
+
+    /// Returns how the cell should be shown on the losing board: every mine
+    /// uncovered and every wrong flag marked. While playing or after a win,
+    /// cells are always unchanged.
+    pub fn end_view_at(&self, x: u32, y: u32, z: u32) -> Option<EndView> {
+        if !self.check_point_at(x, y, z) {
+            return None;
+        }
+        if self.state != GridState::Lose {
+            return Some(EndView::Unchanged);
+        }
+        if self.exploded == Some((x, y, z)) {
+            return Some(EndView::Exploded);
+        }
+
+        let cell = self.cell(x, y, z);
+        let view = match cell.state {
+            CellState::Hidden(flags) if flags > cell.mines => EndView::WrongFlag,
+            CellState::Hidden(flags) if flags < cell.mines => EndView::Mine,
+            CellState::Question if cell.mines != 0 => EndView::Mine,
+            _ => EndView::Unchanged,
+        };
+        Some(view)
+    }
+
+    /// Returns the board's 3BV: the fewest clicks that clear it without
+    /// flags. Each opening takes one click, and so does every safe cell that
+    /// no opening uncovers.
+    pub fn bbbv(&self) -> u32 {
+        let mut uncovered = HashSet::new();
+        let mut clicks = 0;
+
+        let points: Vec<(u32, u32, u32)> = (0..self.depth)
+            .flat_map(|z| (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y, z))))
+            .filter(|&(x, y, z)| self.check_point_at(x, y, z) && self.cell(x, y, z).mines == 0)
+            .collect();
+
+        for &(x, y, z) in &points {
+            let cell = self.cell(x, y, z);
+            if cell.surrounding_mines != 0 || uncovered.contains(&(x, y, z)) {
+                continue;
+            }
+            // Flood the opening like `reveal` would.
+            clicks += 1;
+            uncovered.insert((x, y, z));
+            let mut stack = vec![(x, y, z)];
+            while let Some((x, y, z)) = stack.pop() {
+                for n in self.get_neighbors_at(x, y, z) {
+                    if n.mines == 0 && uncovered.insert((n.x, n.y, n.z)) && n.surrounding_mines == 0 {
+                        stack.push((n.x, n.y, n.z));
+                    }
+                }
+            }
+        }
+
+        clicks + points.iter().filter(|p| !uncovered.contains(p)).count() as u32
+    }
+
     pub fn get_neighbors(&self, x: u32, y: u32) -> Vec<Cell> {
+        self.get_neighbors_at(x, y, 0)
+    }
+
+    pub fn get_neighbors_at(&self, x: u32, y: u32, z: u32) -> Vec<Cell> {
         // TODO: Look into using a stack-allocated vector type?
         let mut neighbors = Vec::with_capacity(8);
+        let offsets = self.neighborhood.offsets(x, y);
+        let layers: &[i64] = if self.depth > 1 { &[-1, 0, 1] } else { &[0] };
 
-        for j in -1..2i32 {
-            for i in -1..2i32 {
-                if i != 0 || j != 0 {
-                    if let Some(cell) = self.get_cell((x as i32 + i) as u32,
-                                                      (y as i32 + j) as u32) {
-                        neighbors.push(cell);
+        for &k in layers {
+            // Other layers also include the cell straight above or below.
+            let straight = if k != 0 { Some(&(0, 0)) } else { None };
+            for &(i, j) in offsets.iter().chain(straight) {
+                let point = self.wrap_point_at(x as i64 + i as i64, y as i64 + j as i64, z as i64 + k);
+                if let Some((nx, ny, nz)) = point {
+                    // On a small torus, several offsets can wrap to one cell.
+                    if (nx, ny, nz) == (x, y, z) ||
+                        neighbors.iter().any(|c: &Cell| (c.x, c.y, c.z) == (nx, ny, nz)) {
+                        continue;
                     }
+                    neighbors.push(self.cell(nx, ny, nz).clone());
                 }
             }
         }
         neighbors
     }
 
-    fn count_surrounding_mines(&self, x: u32, y: u32) -> u8 {
-        self.get_neighbors(x, y).iter()
-            .map(|cell| cell.mines)
-            .sum()
+    fn count_surrounding_mines(&self, x: u32, y: u32, z: u32) -> u8 {
+        self.get_neighbors_at(x, y, z).iter()
+            .fold(0u8, |sum, cell| sum.saturating_add(cell.mines))
+    }
+
+    /// Sets a cell's state, keeping the flag count in sync.
+    fn set_state(&mut self, x: u32, y: u32, z: u32, state: CellState) {
+        let cell = &mut self.cells[(z * self.height + y) as usize][x as usize];
+        if let CellState::Hidden(flags) = cell.state {
+            self.mines_flagged -= flags as u32;
+        }
+        if let CellState::Hidden(flags) = state {
+            self.mines_flagged += flags as u32;
+        }
+        cell.state = state;
+    }
+
+    /// Ends the game as won, flagging every mine left like classic clients
+    /// do.
+    fn win(&mut self) {
+        self.state = GridState::Win;
+        for cell in self.cells.iter_mut().flatten() {
+            if cell.mines != 0 {
+                cell.state = CellState::Hidden(cell.mines);
+            }
+        }
+        self.mines_flagged = self.mines;
     }
 
-    fn count_surrounding_flags(&self, x: u32, y: u32) -> u8 {
+    fn count_surrounding_flags(&self, x: u32, y: u32, z: u32) -> u8 {
         let mut flags = 0;
-        for cell in self.get_neighbors(x, y) {
+        for cell in self.get_neighbors_at(x, y, z) {
             if let CellState::Hidden(f) = cell.state {
                 flags += f;
             }
@@ -168,41 +664,57 @@ impl MineGrid {
     }
 
     pub fn toggle_flag(&mut self, x: u32, y: u32) {
-        if !self.check_point(x, y) {
+        self.toggle_flag_at(x, y, 0);
+    }
+
+    pub fn toggle_flag_at(&mut self, x: u32, y: u32, z: u32) {
+        if !self.check_point_at(x, y, z) {
             return;
         }
 
-        let cell = &mut self.cells[y as usize][x as usize];
-        if let CellState::Hidden(flags) = cell.state {
-            cell.state = CellState::Hidden((flags + 1) % (self.max_mines + 1))
-        }
+        let state = match self.cell(x, y, z).state {
+            CellState::Hidden(flags) if flags < self.max_mines => CellState::Hidden(flags + 1),
+            CellState::Hidden(_) if self.question_marks => CellState::Question,
+            CellState::Hidden(_) | CellState::Question => CellState::Hidden(0),
+            CellState::Revealed => return,
+        };
+        self.set_state(x, y, z, state);
     }
 
     pub fn reveal(&mut self, x: u32, y: u32) {
-        if !self.check_point(x, y) {
+        self.reveal_at(x, y, 0);
+    }
+
+    pub fn reveal_at(&mut self, x: u32, y: u32, z: u32) {
+        // Once the game is over, stop revealing (e.g. the rest of a chord).
+        if !self.check_point_at(x, y, z) || self.state != GridState::Play {
             return;
         }
 
-        let cell = self.cells[y as usize][x as usize].clone();
+        let cell = self.cell(x, y, z).clone();
         match cell.state {
-            CellState::Hidden(0) => {
+            CellState::Hidden(0) | CellState::Question => {
                 // Try to reveal.
-                self.cells[y as usize][x as usize].state = CellState::Revealed;
+                self.set_state(x, y, z, CellState::Revealed);
 
                 if cell.mines != 0 {
                     self.state = GridState::Lose;
+                    self.exploded = Some((x, y, z));
                     return;
                 }
 
                 self.spaces_left -= 1;
                 if self.spaces_left == 0 {
-                    self.state = GridState::Win;
+                    self.win();
                     return;
                 }
 
                 if cell.surrounding_mines == 0 {
-                    for n in self.get_neighbors(x, y) {
-                        self.reveal(n.x, n.y);
+                    // Flood fill, skipping revealed cells so we don't chord.
+                    for n in self.get_neighbors_at(x, y, z) {
+                        if n.state != CellState::Revealed {
+                            self.reveal_at(n.x, n.y, n.z);
+                        }
                     }
                 }
             },
@@ -213,13 +725,13 @@ impl MineGrid {
                 if cell.surrounding_mines == 0 {
                     return;
                 }
-                let flags = self.count_surrounding_flags(x, y);
+                let flags = self.count_surrounding_flags(x, y, z);
                 if cell.surrounding_mines != flags {
                     return;
                 }
-                for neighbor in self.get_neighbors(x, y) {
-                    if let CellState::Hidden(_) = neighbor.state {
-                        self.reveal(neighbor.x, neighbor.y);
+                for neighbor in self.get_neighbors_at(x, y, z) {
+                    if neighbor.state != CellState::Revealed {
+                        self.reveal_at(neighbor.x, neighbor.y, neighbor.z);
                     }
                 }
             },
@@ -251,16 +763,151 @@ mod minegrid_test {
         assert_eq!(GridState::Play, grid.state());
     }
 
+    #[test]
+    fn test_with_seed() {
+        let grid = MineGrid::with_seed(16, 16, 40, 7);
+        let other = MineGrid::with_seed(16, 16, 40, 7);
+
+        assert_eq!(Some(7), grid.seed());
+        assert_eq!(grid.bbbv(), other.bbbv());
+        for j in 0..16 {
+            for i in 0..16 {
+                assert_eq!(grid.get_cell(i, j).unwrap().mines(), other.get_cell(i, j).unwrap().mines());
+            }
+        }
+        assert!(MineGrid::new(4, 4, 4).seed().is_some());
+        assert_eq!(None, MineGrid::with_mines(3, 3, &[(0, 0)]).seed());
+    }
+
+    #[test]
+    fn test_to_builder() {
+        let mut grid = MineGridBuilder::new(4, 3, 0)
+            .mine_points(&[(0, 0), (0, 0), (3, 2)])
+            .revealed_points(&[(2, 0)])
+            .max_mines(2)
+            .topology(Topology::Torus)
+            .build();
+        let builder = grid.to_builder();
+        grid.toggle_flag(1, 1);
+        grid.reveal(0, 0);
+        assert_eq!(GridState::Lose, grid.state());
+
+        let fresh = builder.build();
+        assert_eq!(GridState::Play, fresh.state());
+        assert_eq!((4, 3, 3), (fresh.width(), fresh.height(), fresh.mines()));
+        assert_eq!(Topology::Torus, fresh.topology());
+        assert_eq!(None, fresh.seed());
+        assert_eq!(2, fresh.get_cell(0, 0).unwrap().mines());
+        assert_eq!(1, fresh.get_cell(3, 2).unwrap().mines());
+        assert_eq!(CellState::Hidden(0), fresh.get_cell(1, 1).unwrap().state());
+        assert_eq!(CellState::Revealed, fresh.get_cell(2, 0).unwrap().state());
+
+        let grid = MineGridBuilder::new(5, 5, 6).depth(2).seed(9).build();
+        let fresh = grid.to_builder().build();
+        assert_eq!(Some(9), fresh.seed());
+        for (x, y, z) in (0..2).flat_map(|z| (0..5).flat_map(move |y| (0..5).map(move |x| (x, y, z)))) {
+            assert_eq!(grid.get_cell_at(x, y, z).unwrap().mines(), fresh.get_cell_at(x, y, z).unwrap().mines());
+        }
+    }
+
+    #[test]
+    fn test_send() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<MineGrid>();
+        assert_send_sync::<MineGridBuilder>();
+    }
+
+    #[test]
+    fn test_with_mines() {
+        let grid = MineGrid::with_mines(3, 3, &[(0, 0), (2, 1)]);
+
+        assert_eq!(2, grid.mines());
+        assert_eq!(1, grid.get_cell(0, 0).unwrap().mines());
+        assert_eq!(0, grid.get_cell(1, 0).unwrap().mines());
+        assert_eq!(1, grid.get_cell(2, 1).unwrap().mines());
+        assert_eq!(2, grid.get_cell(1, 1).unwrap().surrounding_mines());
+        assert_eq!(0, grid.get_cell(0, 2).unwrap().surrounding_mines());
+    }
+
+    #[test]
+    fn test_builder_max_mines() {
+        let (width, height, mines) = (4, 4, 40);
+
+        let grid = MineGridBuilder::new(width, height, mines).max_mines(3).build();
+
+        let mut mine_count = 0;
+        for j in 0..height {
+            for i in 0..width {
+                let cell = grid.get_cell(i, j).unwrap();
+                assert!(cell.mines() <= 3);
+                mine_count += cell.mines() as u32;
+            }
+        }
+        assert_eq!(mines, mine_count);
+        assert_eq!(mines, grid.mines());
+        assert_eq!(3, grid.max_mines());
+    }
+
+    #[test]
+    fn test_multi_mine_counts() {
+        // 2 mines at (0, 0), 3 at (2, 0).
+        let grid = MineGrid::with_mines(3, 2, &[(0, 0), (0, 0), (2, 0), (2, 0), (2, 0)]);
+
+        assert_eq!(5, grid.mines());
+        assert_eq!(2, grid.get_cell(0, 0).unwrap().mines());
+        assert_eq!(5, grid.get_cell(1, 0).unwrap().surrounding_mines());
+        assert_eq!(5, grid.get_cell(1, 1).unwrap().surrounding_mines());
+        assert_eq!(2, grid.get_cell(0, 1).unwrap().surrounding_mines());
+    }
+
+    #[test]
+    fn test_multi_mine_counts_saturate() {
+        // 300 mines at (0, 0) and 1 at (2, 0): the cell keeps u8::MAX and
+        // the count between them saturates.
+        let mut points = vec![(0, 0); 300];
+        points.push((2, 0));
+        let grid = MineGrid::with_mines(3, 1, &points);
+
+        assert_eq!(255, grid.get_cell(0, 0).unwrap().mines());
+        assert_eq!(256, grid.mines());
+        assert_eq!(255, grid.max_mines());
+        assert_eq!(255, grid.get_cell(1, 0).unwrap().surrounding_mines());
+    }
+
+    #[test]
+    fn test_multi_mine_flag_cycle() {
+        let mut grid = MineGridBuilder::new(3, 3, 2).max_mines(2).build();
+
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(1), grid.get_cell(0, 0).unwrap().state());
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(2), grid.get_cell(0, 0).unwrap().state());
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(0), grid.get_cell(0, 0).unwrap().state());
+    }
+
+    #[test]
+    fn test_multi_mine_win() {
+        // Three safe cells around a stack of 3 mines.
+        let mut grid = MineGrid::with_mines(2, 2, &[(0, 0), (0, 0), (0, 0)]);
+
+        grid.reveal(1, 0);
+        grid.reveal(0, 1);
+        assert_eq!(GridState::Play, grid.state());
+        grid.reveal(1, 1);
+        assert_eq!(GridState::Win, grid.state());
+    }
+
     #[test]
     fn test_check_point() {
         let (width, height, mines) = (10, 10, 10);
 
         let grid = MineGrid::new(width, height, mines);
 
-        assert_eq!(true, grid.check_point(0, 0));
-        assert_eq!(true, grid.check_point(1, 0));
-        assert_eq!(true, grid.check_point(9, 9));
-        assert_eq!(false, grid.check_point(10, 0));
+        assert!(grid.check_point(0, 0));
+        assert!(grid.check_point(1, 0));
+        assert!(grid.check_point(9, 9));
+        assert!(!grid.check_point(10, 0));
     }
 
     #[test]
@@ -274,6 +921,275 @@ mod minegrid_test {
         assert_eq!(8, grid.get_neighbors(1, 1).len());
     }
 
+    #[test]
+    fn test_torus_neighbors() {
+        let grid = MineGridBuilder::new(10, 10, 10).topology(Topology::Torus).build();
+
+        assert_eq!(8, grid.get_neighbors(0, 0).len());
+        assert_eq!(8, grid.get_neighbors(9, 5).len());
+        assert_eq!(Some((9, 0)), grid.wrap_point(-1, 10));
+
+        // Wrapped offsets that land on the same cell only count once.
+        let grid = MineGridBuilder::new(2, 2, 0).topology(Topology::Torus).build();
+        assert_eq!(3, grid.get_neighbors(0, 0).len());
+        let grid = MineGridBuilder::new(1, 1, 0).topology(Topology::Torus).build();
+        assert_eq!(0, grid.get_neighbors(0, 0).len());
+    }
+
+    #[test]
+    fn test_torus_reveal() {
+        // 1.1*
+        // 1.11
+        // ....
+        // 1.11
+        let mut grid = MineGridBuilder::new(4, 4, 0)
+            .topology(Topology::Torus)
+            .mine_points(&[(3, 0)])
+            .build();
+
+        assert_eq!(1, grid.get_cell(0, 0).unwrap().surrounding_mines());
+        assert_eq!(1, grid.get_cell(0, 3).unwrap().surrounding_mines());
+        assert_eq!(0, grid.get_cell(1, 0).unwrap().surrounding_mines());
+
+        // The flood fill wraps around the edges and opens everything.
+        grid.reveal(1, 2);
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_hex_neighbors() {
+        let grid = MineGridBuilder::new(10, 10, 10).tiling(Tiling::Hex).build();
+
+        assert_eq!(2, grid.get_neighbors(0, 0).len());
+        assert_eq!(5, grid.get_neighbors(0, 1).len());
+        assert_eq!(6, grid.get_neighbors(1, 1).len());
+        assert_eq!(6, grid.get_neighbors(1, 2).len());
+
+        let points = |x, y| -> Vec<(u32, u32)> {
+            grid.get_neighbors(x, y).iter().map(|c| (c.x, c.y)).collect()
+        };
+        assert_eq!(vec![(3, 1), (4, 1), (3, 2), (5, 2), (3, 3), (4, 3)], points(4, 2));
+        assert_eq!(vec![(4, 2), (5, 2), (3, 3), (5, 3), (4, 4), (5, 4)], points(4, 3));
+    }
+
+    #[test]
+    fn test_hex_reveal() {
+        //  * 1 . .
+        //   1 . . .
+        //  . . . .
+        let mut grid = MineGridBuilder::new(4, 3, 0)
+            .tiling(Tiling::Hex)
+            .mine_points(&[(0, 0)])
+            .build();
+
+        assert_eq!(1, grid.get_cell(1, 0).unwrap().surrounding_mines());
+        assert_eq!(1, grid.get_cell(0, 1).unwrap().surrounding_mines());
+        assert_eq!(0, grid.get_cell(1, 1).unwrap().surrounding_mines());
+        assert_eq!(0, grid.get_cell(0, 2).unwrap().surrounding_mines());
+
+        grid.reveal(3, 2);
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_orthogonal_reveal() {
+        // * 1 .
+        // 1 . .
+        // . . .
+        let mut grid = MineGridBuilder::new(3, 3, 0)
+            .neighborhood(neighborhood::Orthogonal)
+            .mine_points(&[(0, 0)])
+            .build();
+
+        assert_eq!(0, grid.get_cell(1, 1).unwrap().surrounding_mines());
+        assert_eq!(3, grid.get_neighbors(1, 0).len());
+
+        grid.reveal(2, 2);
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_knight_neighbors() {
+        let grid = MineGridBuilder::new(5, 5, 0)
+            .neighborhood(neighborhood::Knight)
+            .mine_points(&[(2, 2)])
+            .build();
+
+        assert_eq!(8, grid.get_neighbors(2, 2).len());
+        assert_eq!(2, grid.get_neighbors(0, 0).len());
+        assert_eq!(1, grid.get_cell(0, 1).unwrap().surrounding_mines());
+        assert_eq!(0, grid.get_cell(1, 1).unwrap().surrounding_mines());
+        assert_eq!(1, grid.get_cell(4, 3).unwrap().surrounding_mines());
+    }
+
+    #[test]
+    fn test_extended_counts() {
+        let grid = MineGridBuilder::new(5, 5, 0)
+            .neighborhood(neighborhood::Extended)
+            .mine_points(&[(0, 0), (4, 4), (2, 0)])
+            .build();
+
+        assert_eq!(24, grid.get_neighbors(2, 2).len());
+        assert_eq!(3, grid.get_cell(2, 2).unwrap().surrounding_mines());
+        assert_eq!(2, grid.get_cell(0, 2).unwrap().surrounding_mines());
+        assert_eq!(1, grid.get_cell(4, 0).unwrap().surrounding_mines());
+    }
+
+    #[test]
+    fn test_mask() {
+        // A ring around a single hole.
+        let mut grid = MineGridBuilder::new(0, 0, 0)
+            .mask(Mask::from_ascii("###\n# #\n###"))
+            .mine_points(&[(0, 0), (1, 1)])
+            .build();
+
+        assert_eq!(3, grid.width());
+        assert_eq!(3, grid.height());
+        assert_eq!(1, grid.mines());
+        assert!(!grid.check_point(1, 1));
+        assert!(grid.get_cell(1, 1).is_none());
+        assert_eq!(None, grid.wrap_point(1, 1));
+        assert_eq!(4, grid.get_neighbors(1, 0).len());
+        assert_eq!(1, grid.get_cell(1, 0).unwrap().surrounding_mines());
+
+        // The flood fill goes around the hole, and the hole isn't a space left.
+        grid.reveal(2, 2);
+        assert_eq!(CellState::Revealed, grid.get_cell(0, 1).unwrap().state());
+        assert_eq!(CellState::Hidden(1), grid.get_cell(0, 0).unwrap().state());
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_mask_random_mines() {
+        let mask = Mask::from_ascii("#  #\n    \n#  #");
+        let grid = MineGridBuilder::new(0, 0, 4).mask(mask).build();
+
+        for &(x, y) in &[(0, 0), (3, 0), (0, 2), (3, 2)] {
+            assert_eq!(1, grid.get_cell(x, y).unwrap().mines());
+        }
+    }
+
+    #[test]
+    fn test_mask_torus() {
+        let grid = MineGridBuilder::new(0, 0, 0)
+            .mask(Mask::from_ascii("## \n###"))
+            .topology(Topology::Torus)
+            .build();
+
+        assert_eq!(None, grid.wrap_point(-1, 0));
+        assert_eq!(Some((2, 1)), grid.wrap_point(-1, 1));
+        assert_eq!(4, grid.get_neighbors(0, 0).len());
+    }
+
+    #[test]
+    fn test_3d_neighbors() {
+        let grid = MineGridBuilder::new(3, 3, 0)
+            .depth(3)
+            .mine_points_at(&[(0, 0, 0), (1, 1, 2)])
+            .build();
+
+        assert_eq!(3, grid.depth());
+        assert_eq!(2, grid.mines());
+        assert_eq!(26, grid.get_neighbors_at(1, 1, 1).len());
+        assert_eq!(7, grid.get_neighbors_at(0, 0, 0).len());
+        assert_eq!(2, grid.get_cell_at(1, 1, 1).unwrap().surrounding_mines());
+        assert_eq!(2, grid.get_cell_at(0, 0, 1).unwrap().surrounding_mines());
+        assert_eq!(1, grid.get_cell_at(2, 2, 2).unwrap().surrounding_mines());
+        assert_eq!(0, grid.get_cell_at(2, 2, 0).unwrap().surrounding_mines());
+        assert!(grid.get_cell_at(0, 0, 3).is_none());
+    }
+
+    #[test]
+    fn test_3d_reveal() {
+        // The flood fill crosses layers.
+        let mut grid = MineGridBuilder::new(4, 4, 0)
+            .depth(2)
+            .mine_points_at(&[(0, 0, 1)])
+            .build();
+
+        grid.reveal_at(3, 3, 0);
+        assert_eq!(CellState::Revealed, grid.get_cell_at(3, 3, 1).unwrap().state());
+        assert_eq!(CellState::Hidden(0), grid.get_cell_at(0, 0, 1).unwrap().state());
+
+        // Walled in by numbers on both layers.
+        assert_eq!(CellState::Hidden(0), grid.get_cell_at(0, 0, 0).unwrap().state());
+        grid.reveal_at(0, 0, 0);
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_3d_chord() {
+        let mut grid = MineGridBuilder::new(3, 3, 0)
+            .depth(2)
+            .mine_points_at(&[(0, 0, 1), (2, 2, 1)])
+            .build();
+
+        grid.reveal_at(1, 1, 0);
+        assert_eq!(2, grid.get_cell_at(1, 1, 0).unwrap().surrounding_mines());
+        grid.toggle_flag_at(0, 0, 1);
+        grid.toggle_flag_at(2, 2, 1);
+        assert_eq!(0, grid.mines_left());
+        grid.reveal_at(1, 1, 0);
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_chord_wrong_flag() {
+        let mut grid = MineGrid::from_layout("X1*\n.1.").unwrap();
+
+        grid.reveal(1, 0);
+        assert_eq!(GridState::Lose, grid.state());
+        assert_eq!(CellState::Revealed, grid.get_cell(2, 0).unwrap().state());
+    }
+
+    #[test]
+    fn test_win_flags_mines() {
+        let mut grid = MineGridBuilder::new(3, 1, 0)
+            .max_mines(3)
+            .mine_points(&[(0, 0), (0, 0), (2, 0)])
+            .build();
+        grid.toggle_flag(2, 0);
+        grid.toggle_flag(2, 0);
+        assert_eq!(1, grid.mines_left());
+
+        grid.reveal(1, 0);
+        assert_eq!(GridState::Win, grid.state());
+        assert_eq!(CellState::Hidden(2), grid.get_cell(0, 0).unwrap().state());
+        assert_eq!(CellState::Hidden(1), grid.get_cell(2, 0).unwrap().state());
+        assert_eq!(0, grid.mines_left());
+    }
+
+    #[test]
+    fn test_bbbv() {
+        //  *2110
+        //  12*10
+        //  01121
+        //  0001*
+        let grid = MineGrid::with_mines(5, 4, &[(0, 0), (2, 1), (4, 3)]);
+        assert_eq!(4, grid.bbbv());
+
+        let grid = MineGrid::from_layout("*.*\n...").unwrap();
+        assert_eq!(4, grid.bbbv());
+
+        let grid = MineGrid::from_layout("*.*\n***").unwrap();
+        assert_eq!(1, grid.bbbv());
+    }
+
+    #[test]
+    fn test_end_view() {
+        let mut grid = MineGrid::from_layout("**FX\n....\n....").unwrap();
+        assert_eq!(Some(EndView::Unchanged), grid.end_view(1, 0));
+
+        grid.reveal(0, 0);
+        assert_eq!(GridState::Lose, grid.state());
+        assert_eq!(Some(EndView::Exploded), grid.end_view(0, 0));
+        assert_eq!(Some(EndView::Mine), grid.end_view(1, 0));
+        assert_eq!(Some(EndView::Unchanged), grid.end_view(2, 0));
+        assert_eq!(Some(EndView::WrongFlag), grid.end_view(3, 0));
+        assert_eq!(Some(EndView::Unchanged), grid.end_view(0, 2));
+        assert_eq!(None, grid.end_view(4, 0));
+    }
+
     #[test]
     fn test_toggle_flag() {
         let (width, height, mines) = (10, 10, 10);
@@ -286,6 +1202,112 @@ mod minegrid_test {
         assert_eq!(CellState::Hidden(0), grid.get_cell(0, 0).unwrap().state());
     }
 
+    #[test]
+    fn test_toggle_question_mark() {
+        let mut grid = MineGrid::new(10, 10, 10);
+        grid.set_question_marks(true);
+
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(1), grid.get_cell(0, 0).unwrap().state());
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Question, grid.get_cell(0, 0).unwrap().state());
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(0), grid.get_cell(0, 0).unwrap().state());
+
+        grid.toggle_flag(0, 0);
+        grid.toggle_flag(0, 0);
+        grid.set_question_marks(false);
+        assert_eq!(CellState::Hidden(0), grid.get_cell(0, 0).unwrap().state());
+    }
+
+    #[test]
+    fn test_question_mark_chord() {
+        // *1.
+        // 11.
+        // ...
+        let mut grid = MineGrid::with_mines(3, 3, &[(0, 0)]);
+        grid.set_question_marks(true);
+
+        // A "?" isn't a flag, so it doesn't satisfy the number.
+        grid.reveal(1, 0);
+        grid.toggle_flag(0, 0);
+        grid.toggle_flag(0, 0);
+        grid.toggle_flag(1, 1);
+        grid.toggle_flag(1, 1);
+        assert_eq!(CellState::Question, grid.get_cell(1, 1).unwrap().state());
+        grid.reveal(1, 0);
+        assert_eq!(CellState::Question, grid.get_cell(0, 0).unwrap().state());
+        assert_eq!(CellState::Question, grid.get_cell(1, 1).unwrap().state());
+
+        // Chording opens "?" neighbors just like unmarked ones.
+        grid.toggle_flag(0, 0);
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(1), grid.get_cell(0, 0).unwrap().state());
+        grid.reveal(1, 0);
+        assert_eq!(CellState::Revealed, grid.get_cell(1, 1).unwrap().state());
+        assert_eq!(GridState::Win, grid.state());
+    }
+
+    #[test]
+    fn test_mines_left() {
+        let mut grid = MineGrid::with_mines(3, 3, &[(0, 0)]);
+        assert_eq!(1, grid.mines_left());
+
+        grid.toggle_flag(0, 0);
+        assert_eq!(0, grid.mines_left());
+
+        // Over-flagging goes negative.
+        grid.toggle_flag(1, 1);
+        grid.toggle_flag(2, 2);
+        assert_eq!(-2, grid.mines_left());
+
+        grid.toggle_flag(1, 1);
+        assert_eq!(-1, grid.mines_left());
+
+        // Question marks don't count as flags.
+        grid.set_question_marks(true);
+        grid.toggle_flag(2, 2);
+        assert_eq!(CellState::Question, grid.get_cell(2, 2).unwrap().state());
+        assert_eq!(0, grid.mines_left());
+    }
+
+    #[test]
+    fn test_mines_left_multi_flags() {
+        let mut grid = MineGrid::with_mines(3, 3, &[(0, 0), (0, 0), (2, 2), (2, 2), (2, 2)]);
+        assert_eq!(3, grid.max_mines());
+
+        grid.toggle_flag(0, 0);
+        grid.toggle_flag(0, 0);
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(3), grid.get_cell(0, 0).unwrap().state());
+        assert_eq!(2, grid.mines_left());
+
+        grid.toggle_flag(0, 0);
+        assert_eq!(CellState::Hidden(0), grid.get_cell(0, 0).unwrap().state());
+        assert_eq!(5, grid.mines_left());
+    }
+
+    #[test]
+    fn test_mines_left_after_chord() {
+        // *1.
+        // 11.
+        // ...
+        let mut grid = MineGrid::with_mines(3, 3, &[(0, 0)]);
+
+        // A wrong flag makes the chord open the mine.
+        grid.reveal(1, 0);
+        grid.toggle_flag(1, 1);
+        grid.reveal(1, 0);
+        assert_eq!(GridState::Lose, grid.state());
+        assert_eq!(CellState::Revealed, grid.get_cell(0, 0).unwrap().state());
+        assert_eq!(CellState::Hidden(1), grid.get_cell(1, 1).unwrap().state());
+        assert_eq!(0, grid.mines_left());
+
+        // Revealing stops once the game is lost.
+        grid.reveal(2, 2);
+        assert_eq!(CellState::Hidden(0), grid.get_cell(2, 2).unwrap().state());
+    }
+
     #[test]
     fn test_reveal_empty_grid() {
         let (width, height, mines) = (10, 10, 0);
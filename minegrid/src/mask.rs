@@ -0,0 +1,110 @@
+//! Irregular board shapes.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Which cells of a grid exist. The rest are holes: they hold no mines, have
+/// no neighbors and are never revealed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    width: u32,
+    height: u32,
+    cells: Vec<bool>,
+}
+
+impl Mask {
+    /// A full rectangle with no holes.
+    pub fn new(width: u32, height: u32) -> Mask {
+        Mask {
+            width,
+            height,
+            cells: vec![true; (width * height) as usize],
+        }
+    }
+
+    /// Reads a mask drawn as text, one row per line. A space is a hole and
+    /// any other character is a cell. Short lines are padded with holes.
+    pub fn from_ascii(text: &str) -> Mask {
+        let lines: Vec<&str> = text.lines().collect();
+        let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as u32;
+        let mut mask = Mask {
+            width,
+            height: lines.len() as u32,
+            cells: vec![false; width as usize * lines.len()],
+        };
+
+        for (y, line) in lines.iter().enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                mask.set(x as u32, y as u32, ch != ' ');
+            }
+        }
+        mask
+    }
+
+    /// Reads a mask from a text file in the format of `from_ascii`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Mask> {
+        Ok(Mask::from_ascii(&fs::read_to_string(path)?))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether (x, y) is a cell rather than a hole or off the mask.
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.cells[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, present: bool) {
+        if x < self.width && y < self.height {
+            self.cells[(y * self.width + x) as usize] = present;
+        }
+    }
+
+    /// Number of cells that exist.
+    pub fn count(&self) -> u32 {
+        self.cells.iter().filter(|&&present| present).count() as u32
+    }
+
+    /// Whether the mask has no holes.
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().all(|&present| present)
+    }
+}
+
+#[cfg(test)]
+mod mask_test {
+    use super::*;
+
+    #[test]
+    fn test_from_ascii() {
+        let mask = Mask::from_ascii(" ### \n## ##\n #\n");
+
+        assert_eq!(5, mask.width());
+        assert_eq!(3, mask.height());
+        assert_eq!(8, mask.count());
+        assert!(!mask.is_full());
+        assert!(!mask.contains(0, 0));
+        assert!(mask.contains(1, 0));
+        assert!(!mask.contains(2, 1));
+        assert!(mask.contains(1, 2));
+        assert!(!mask.contains(4, 2));
+        assert!(!mask.contains(5, 0));
+    }
+
+    #[test]
+    fn test_new() {
+        let mut mask = Mask::new(3, 2);
+        assert!(mask.is_full());
+        assert_eq!(6, mask.count());
+
+        mask.set(1, 1, false);
+        assert!(!mask.contains(1, 1));
+        assert_eq!(5, mask.count());
+    }
+}
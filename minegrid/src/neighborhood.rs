@@ -0,0 +1,141 @@
+//! Rules for which cells count as neighbors of each other.
+
+/// Decides which cells neighbor a cell, and so which mines it counts and
+/// which cells a flood fill or chord opens.
+pub trait Neighborhood {
+    /// Offsets from (x, y) to each of its neighbors. They may point off the
+    /// grid; the grid's topology decides whether those wrap or are dropped.
+    fn offsets(&self, x: u32, y: u32) -> &[(i32, i32)];
+}
+
+/// The classic 8 surrounding cells.
+#[derive(Clone, Copy, Debug)]
+pub struct Square;
+
+/// The 6 cells around a hexagon, in "odd-r" offset coordinates where odd rows
+/// are shifted half a cell to the right.
+#[derive(Clone, Copy, Debug)]
+pub struct Hex;
+
+/// Only the 4 cells sharing an edge.
+#[derive(Clone, Copy, Debug)]
+pub struct Orthogonal;
+
+/// The 8 cells a chess knight could jump to.
+#[derive(Clone, Copy, Debug)]
+pub struct Knight;
+
+/// The 24 other cells in the 5x5 block centered on the cell.
+#[derive(Clone, Copy, Debug)]
+pub struct Extended;
+
+const SQUARE: &[(i32, i32)] = &[
+    (-1, -1), (0, -1), (1, -1),
+    (-1, 0), (1, 0),
+    (-1, 1), (0, 1), (1, 1),
+];
+const HEX_EVEN_ROW: &[(i32, i32)] = &[
+    (-1, -1), (0, -1),
+    (-1, 0), (1, 0),
+    (-1, 1), (0, 1),
+];
+const HEX_ODD_ROW: &[(i32, i32)] = &[
+    (0, -1), (1, -1),
+    (-1, 0), (1, 0),
+    (0, 1), (1, 1),
+];
+const ORTHOGONAL: &[(i32, i32)] = &[
+    (0, -1),
+    (-1, 0), (1, 0),
+    (0, 1),
+];
+const KNIGHT: &[(i32, i32)] = &[
+    (-1, -2), (1, -2),
+    (-2, -1), (2, -1),
+    (-2, 1), (2, 1),
+    (-1, 2), (1, 2),
+];
+const EXTENDED: &[(i32, i32)] = &[
+    (-2, -2), (-1, -2), (0, -2), (1, -2), (2, -2),
+    (-2, -1), (-1, -1), (0, -1), (1, -1), (2, -1),
+    (-2, 0), (-1, 0), (1, 0), (2, 0),
+    (-2, 1), (-1, 1), (0, 1), (1, 1), (2, 1),
+    (-2, 2), (-1, 2), (0, 2), (1, 2), (2, 2),
+];
+
+impl Neighborhood for Square {
+    fn offsets(&self, _x: u32, _y: u32) -> &[(i32, i32)] {
+        SQUARE
+    }
+}
+
+impl Neighborhood for Hex {
+    fn offsets(&self, _x: u32, y: u32) -> &[(i32, i32)] {
+        if y.is_multiple_of(2) {
+            HEX_EVEN_ROW
+        } else {
+            HEX_ODD_ROW
+        }
+    }
+}
+
+impl Neighborhood for Orthogonal {
+    fn offsets(&self, _x: u32, _y: u32) -> &[(i32, i32)] {
+        ORTHOGONAL
+    }
+}
+
+impl Neighborhood for Knight {
+    fn offsets(&self, _x: u32, _y: u32) -> &[(i32, i32)] {
+        KNIGHT
+    }
+}
+
+impl Neighborhood for Extended {
+    fn offsets(&self, _x: u32, _y: u32) -> &[(i32, i32)] {
+        EXTENDED
+    }
+}
+
+#[cfg(test)]
+mod neighborhood_test {
+    use super::*;
+
+    #[test]
+    fn test_offsets_exclude_self() {
+        let rules: [&dyn Neighborhood; 5] = [&Square, &Hex, &Orthogonal, &Knight, &Extended];
+        for rule in rules.iter() {
+            for y in 0..2 {
+                assert!(!rule.offsets(0, y).contains(&(0, 0)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_offsets_are_symmetric() {
+        // If b neighbors a then a neighbors b.
+        let rules: [&dyn Neighborhood; 4] = [&Square, &Orthogonal, &Knight, &Extended];
+        for rule in rules.iter() {
+            for &(dx, dy) in rule.offsets(0, 0) {
+                assert!(rule.offsets(0, 0).contains(&(-dx, -dy)));
+            }
+        }
+
+        for y in 0..2i32 {
+            for &(dx, dy) in Hex.offsets(0, y as u32) {
+                let back = Hex.offsets(0, (y + dy).rem_euclid(2) as u32);
+                assert!(back.contains(&(-dx, -dy)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_counts() {
+        assert_eq!(8, Square.offsets(0, 0).len());
+        assert_eq!(6, Hex.offsets(0, 0).len());
+        assert_eq!(6, Hex.offsets(0, 1).len());
+        assert_eq!(4, Orthogonal.offsets(0, 0).len());
+        assert_eq!(8, Knight.offsets(0, 0).len());
+        assert_eq!(24, Extended.offsets(0, 0).len());
+    }
+}
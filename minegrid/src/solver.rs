@@ -0,0 +1,201 @@
+//! Checks whether a board can be finished without guessing.
+
+use crate::{CellState, MineGrid};
+
+/// What the solver has worked out about a cell.
+#[derive(Clone, Copy, PartialEq)]
+enum Known {
+    Unknown,
+    Safe,
+    Mine,
+}
+
+struct Solver<'a> {
+    grid: &'a MineGrid,
+    known: Vec<Known>,
+    mines_found: u32,
+    safe_left: u32,
+}
+
+/// A revealed number: how many of these unknown cells are mines.
+struct Constraint {
+    cells: Vec<(u32, u32, u32)>,
+    mines: u32,
+}
+
+/// Whether the rest of the board follows by logic from the cells revealed so
+/// far, without ever having to guess. Flags are ignored, since they may be
+/// wrong. Boards with more than one mine per cell are never solvable here.
+pub fn is_solvable(grid: &MineGrid) -> bool {
+    if grid.max_mines() > 1 {
+        return false;
+    }
+    Solver::new(grid).solve()
+}
+
+impl<'a> Solver<'a> {
+    fn new(grid: &'a MineGrid) -> Solver<'a> {
+        let mut solver = Solver {
+            grid,
+            known: vec![Known::Unknown; (grid.width() * grid.height() * grid.depth()) as usize],
+            mines_found: 0,
+            safe_left: 0,
+        };
+        for (x, y, z) in solver.points() {
+            let cell = grid.cell(x, y, z);
+            if cell.state == CellState::Revealed && cell.mines == 0 {
+                let index = solver.index(x, y, z);
+                solver.known[index] = Known::Safe;
+            } else if cell.mines == 0 {
+                solver.safe_left += 1;
+            }
+        }
+        solver
+    }
+
+    fn index(&self, x: u32, y: u32, z: u32) -> usize {
+        ((z * self.grid.height() + y) * self.grid.width() + x) as usize
+    }
+
+    fn get(&self, (x, y, z): (u32, u32, u32)) -> Known {
+        self.known[self.index(x, y, z)]
+    }
+
+    /// Every cell of the grid, skipping holes.
+    fn points(&self) -> Vec<(u32, u32, u32)> {
+        let grid = self.grid;
+        let mut points = Vec::new();
+        for z in 0..grid.depth() {
+            for y in 0..grid.height() {
+                for x in 0..grid.width() {
+                    if grid.check_point_at(x, y, z) {
+                        points.push((x, y, z));
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    fn solve(&mut self) -> bool {
+        while self.safe_left > 0 {
+            if !self.step() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Makes one round of deductions. Returns false if nothing new was found.
+    fn step(&mut self) -> bool {
+        let constraints = self.constraints();
+        let mut safe = Vec::new();
+        let mut mines = Vec::new();
+
+        // A number that is already satisfied, or needs all its cells.
+        for c in &constraints {
+            if c.mines == 0 {
+                safe.extend(c.cells.iter().cloned());
+            } else if c.mines as usize == c.cells.len() {
+                mines.extend(c.cells.iter().cloned());
+            }
+        }
+
+        // A number whose cells all touch another number: the leftover cells
+        // of the other number hold the difference.
+        if safe.is_empty() && mines.is_empty() {
+            for a in &constraints {
+                for b in &constraints {
+                    if a.cells.len() >= b.cells.len() || !a.cells.iter().all(|p| b.cells.contains(p)) {
+                        continue;
+                    }
+                    let rest = b.cells.iter().filter(|p| !a.cells.contains(p)).cloned();
+                    if b.mines == a.mines {
+                        safe.extend(rest);
+                    } else if (b.mines - a.mines) as usize == b.cells.len() - a.cells.len() {
+                        mines.extend(rest);
+                    }
+                }
+            }
+        }
+
+        // Counting the mines left.
+        if safe.is_empty() && mines.is_empty() {
+            let unknown: Vec<_> = self.points().into_iter().filter(|&p| self.get(p) == Known::Unknown).collect();
+            let mines_left = self.grid.mines() - self.mines_found;
+            if mines_left == 0 {
+                safe = unknown;
+            } else if mines_left as usize == unknown.len() {
+                mines = unknown;
+            }
+        }
+
+        let progress = !safe.is_empty() || !mines.is_empty();
+        for p in safe {
+            self.mark(p, Known::Safe);
+        }
+        for p in mines {
+            self.mark(p, Known::Mine);
+        }
+        progress
+    }
+
+    fn mark(&mut self, (x, y, z): (u32, u32, u32), known: Known) {
+        let index = self.index(x, y, z);
+        if self.known[index] != Known::Unknown {
+            return;
+        }
+        self.known[index] = known;
+        match known {
+            Known::Safe => self.safe_left -= 1,
+            Known::Mine => self.mines_found += 1,
+            Known::Unknown => {},
+        }
+    }
+
+    fn constraints(&self) -> Vec<Constraint> {
+        let mut constraints = Vec::new();
+        for (x, y, z) in self.points() {
+            if self.get((x, y, z)) != Known::Safe {
+                continue;
+            }
+            let mut cells = Vec::new();
+            let mut mines = self.grid.cell(x, y, z).surrounding_mines as u32;
+            for n in self.grid.get_neighbors_at(x, y, z) {
+                match self.get((n.x, n.y, n.z)) {
+                    Known::Unknown => cells.push((n.x, n.y, n.z)),
+                    Known::Mine => mines -= 1,
+                    Known::Safe => {},
+                }
+            }
+            if !cells.is_empty() {
+                constraints.push(Constraint { cells, mines });
+            }
+        }
+        constraints
+    }
+}
+
+#[cfg(test)]
+mod solver_test {
+    use super::*;
+
+    #[test]
+    fn test_solvable() {
+        assert!(is_solvable(&MineGrid::from_layout("o1*.").unwrap()));
+        assert!(is_solvable(&MineGrid::from_layout("*1o\n11o\nooo").unwrap()));
+
+        // Needs the subset rule: the 1 at (0, 1) has its mine in (0, 2) or
+        // (1, 2), so (2, 2) is safe for the 1 at (1, 1).
+        assert!(is_solvable(&MineGrid::from_layout("ooo\n111\n.*.").unwrap()));
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        // A coin flip between the last two cells.
+        assert!(!is_solvable(&MineGrid::from_layout("oo\n11\n*.").unwrap()));
+
+        // Nothing revealed yet.
+        assert!(!is_solvable(&MineGrid::from_layout("*..\n...").unwrap()));
+    }
+}
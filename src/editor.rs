@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+
+use minegrid::{CellState, MineGrid, MineGridBuilder};
+use minegrid::layout;
+use minegrid::solver;
+
+/// Largest width or height the editor allows.
+pub const MAX_SIZE: u32 = 40;
+
+/// Lays out a board by hand: where the mines are and which cells start out
+/// revealed. Keeps a grid built from the layout so far to preview numbers.
+pub struct Editor {
+    width: u32,
+    height: u32,
+    mines: HashSet<(u32, u32)>,
+    opened: HashSet<(u32, u32)>,
+    cursor: (u32, u32),
+    grid: MineGrid,
+}
+
+impl Editor {
+    pub fn new(width: u32, height: u32) -> Editor {
+        let mut editor = Editor {
+            width: width.clamp(1, MAX_SIZE),
+            height: height.clamp(1, MAX_SIZE),
+            mines: HashSet::new(),
+            opened: HashSet::new(),
+            cursor: (0, 0),
+            grid: MineGrid::new(0, 0, 0),
+        };
+        editor.grid = editor.build();
+        editor
+    }
+
+    /// Starts from the mines and revealed cells of the first layer of a grid.
+    pub fn from_grid(grid: &MineGrid) -> Editor {
+        let mut editor = Editor::new(grid.width(), grid.height());
+        for y in 0..editor.height {
+            for x in 0..editor.width {
+                if let Some(cell) = grid.get_cell(x, y) {
+                    if cell.mines() != 0 {
+                        editor.mines.insert((x, y));
+                    } else if cell.state() == CellState::Revealed {
+                        editor.opened.insert((x, y));
+                    }
+                }
+            }
+        }
+        editor.grid = editor.build();
+        editor
+    }
+
+    /// Preview of the board so far.
+    pub fn grid(&self) -> &MineGrid {
+        &self.grid
+    }
+
+    pub fn cursor(&self) -> (u32, u32) {
+        self.cursor
+    }
+
+    /// Whether the cell starts out revealed.
+    pub fn is_opened(&self, x: u32, y: u32) -> bool {
+        self.opened.contains(&(x, y))
+    }
+
+    pub fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let x = (self.cursor.0 as i64 + dx as i64).clamp(0, self.width as i64 - 1);
+        let y = (self.cursor.1 as i64 + dy as i64).clamp(0, self.height as i64 - 1);
+        self.cursor = (x as u32, y as u32);
+    }
+
+    /// Adds or removes a mine under the cursor.
+    pub fn toggle_mine(&mut self) {
+        let point = self.cursor;
+        if !self.mines.remove(&point) {
+            self.mines.insert(point);
+            self.opened.remove(&point);
+        }
+        self.grid = self.build();
+    }
+
+    /// Makes the safe cell under the cursor start out revealed, or not.
+    pub fn toggle_opened(&mut self) {
+        let point = self.cursor;
+        if !self.mines.contains(&point) && !self.opened.remove(&point) {
+            self.opened.insert(point);
+        }
+        self.grid = self.build();
+    }
+
+    /// Grows or shrinks the board, dropping whatever falls off the edge.
+    pub fn resize(&mut self, dw: i32, dh: i32) {
+        let clamp = |size: u32, d: i32| (size as i64 + d as i64).clamp(1, MAX_SIZE as i64) as u32;
+        let (width, height) = (clamp(self.width, dw), clamp(self.height, dh));
+        self.width = width;
+        self.height = height;
+        self.mines.retain(|&(x, y)| x < width && y < height);
+        self.opened.retain(|&(x, y)| x < width && y < height);
+        self.move_cursor(0, 0);
+        self.grid = self.build();
+    }
+
+    /// Whether the board can be finished from the revealed cells without
+    /// guessing.
+    pub fn is_solvable(&self) -> bool {
+        solver::is_solvable(&self.grid)
+    }
+
+    /// The board as a text layout, as read by `MineGrid::from_layout`.
+    pub fn layout(&self) -> String {
+        layout::write(&self.grid)
+    }
+
+    /// A fresh grid to play the board on.
+    pub fn build(&self) -> MineGrid {
+        let mines: Vec<_> = self.mines.iter().cloned().collect();
+        let opened: Vec<_> = self.opened.iter().cloned().collect();
+        MineGridBuilder::new(self.width, self.height, 0)
+            .mine_points(&mines)
+            .revealed_points(&opened)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod editor_test {
+    use super::*;
+
+    #[test]
+    fn test_edit() {
+        let mut editor = Editor::new(4, 2);
+
+        editor.move_cursor(2, 0);
+        editor.toggle_mine();
+        assert_eq!(1, editor.grid().mines());
+        assert_eq!(1, editor.grid().get_cell(1, 1).unwrap().surrounding_mines());
+
+        editor.move_cursor(-2, 1);
+        editor.toggle_opened();
+        assert!(editor.is_opened(0, 1));
+        assert_eq!("..*.\n0...\n", editor.layout());
+
+        // A mine can't start revealed.
+        editor.move_cursor(5, -5);
+        assert_eq!((3, 0), editor.cursor());
+        editor.toggle_mine();
+        editor.toggle_opened();
+        assert!(!editor.is_opened(3, 0));
+        editor.toggle_mine();
+        assert_eq!(1, editor.grid().mines());
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut editor = Editor::new(3, 3);
+        editor.move_cursor(2, 2);
+        editor.toggle_mine();
+
+        editor.resize(-1, 0);
+        assert_eq!(2, editor.grid().width());
+        assert_eq!(0, editor.grid().mines());
+        assert_eq!((1, 2), editor.cursor());
+
+        editor.resize(-5, MAX_SIZE as i32);
+        assert_eq!(1, editor.grid().width());
+        assert_eq!(MAX_SIZE, editor.grid().height());
+    }
+
+    #[test]
+    fn test_solvable() {
+        let editor = Editor::from_grid(&MineGrid::from_layout("o1*.").unwrap());
+        assert!(editor.is_solvable());
+        assert_eq!("01*.\n", editor.layout());
+
+        // A coin flip, until one side is opened.
+        let mut editor = Editor::from_grid(&MineGrid::from_layout("oo\n11\n*.").unwrap());
+        assert!(!editor.is_solvable());
+        editor.move_cursor(1, 2);
+        editor.toggle_opened();
+        assert!(editor.is_solvable());
+    }
+}
@@ -0,0 +1,1792 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use minegrid::{Cell, CellState, GridState, MineGrid, MineGridBuilder, Tiling, Topology};
+use minegrid::infinite::InfiniteGrid;
+use minegrid::mask::Mask;
+
+use editor::Editor;
+use history::{self, Record};
+use puzzle::PuzzlePack;
+use replay::{self, Click, ClickKind, Replay};
+use scores::{Board, HighScores, Score};
+
+/// The most mines a cell can hold in the multimines variant.
+pub const MAX_MINES_PER_CELL: u8 = 3;
+
+/// The most layers a three-dimensional board can have.
+pub const MAX_DEPTH: u32 = 3;
+
+/// The longest name that can go in the high scores.
+pub const MAX_NAME_LEN: usize = 16;
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameState {
+    Play,
+    Lose,
+    Win,
+    New,
+    Edit,
+    Stats,
+    /// Entering a name for the high scores, after a win.
+    Name,
+    Scores,
+    /// Playing back a recorded game.
+    Replay,
+    /// The board is hidden and the clock stopped until the next action.
+    Paused,
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    /// The same board for everyone on a given day.
+    Daily,
+    //Custom(u32, u32, u32),
+}
+
+/// Front-end agnostic input. Each front-end maps its own events onto these.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Reveal,
+    Flag,
+    ToggleQuestionMarks,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    /// Moves the cursor several cells at once.
+    MoveBy(i32, i32),
+    /// Moves the cursor to the edge of the board.
+    JumpUp,
+    JumpDown,
+    JumpLeft,
+    JumpRight,
+    /// Moves the cursor to the next cell in reading order that is still
+    /// hidden.
+    NextHidden,
+    /// Moves the cursor to the next number in reading order with fewer flags
+    /// around it than mines.
+    NextUnsatisfied,
+    LayerUp,
+    LayerDown,
+    NewGame,
+    CycleMaxMines,
+    ToggleTopology,
+    ToggleTiling,
+    CycleDepth,
+    Choose(Difficulty),
+    Endless,
+    Puzzle,
+    Edit,
+    Stats,
+    Scores,
+    ToggleMine,
+    ToggleOpened,
+    Resize(i32, i32),
+    /// Plays the board in the editor.
+    PlayLayout,
+    /// Saves the board in the editor. Left to the front-end.
+    Save,
+    /// Exports the finished game as a replay. Left to the front-end.
+    Export,
+    Pause,
+    /// Plays the same board again from the start.
+    Retry,
+    /// Leaves the pause. Front-ends send it for any input while paused.
+    Resume,
+    Type(char),
+    Backspace,
+    Confirm,
+    Cancel,
+    Quit,
+}
+
+/// Tracks play time. Starts on the first reveal and stops when the game ends.
+/// Time spent paused doesn't count.
+#[derive(Clone, Copy, Debug)]
+pub struct Timer {
+    started: Option<Instant>,
+    stopped: Option<Instant>,
+    paused: Option<Instant>,
+    paused_for: Duration,
+}
+
+/// Clicks made during a game, split the way competitive players count them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Clicks {
+    pub left: u32,
+    pub right: u32,
+    pub chord: u32,
+}
+
+pub struct Game {
+    grid: MineGrid,
+    /// Builds `grid` afresh, for retries.
+    layout: MineGridBuilder,
+    /// Whether `grid` started with cells revealed, which replays can't hold.
+    opened: bool,
+    /// Whether this game replays a board already played.
+    retry: bool,
+    cursor: (u32, u32),
+    /// Replaces `grid` while playing an endless board.
+    endless: Option<InfiniteGrid>,
+    endless_cursor: (i64, i64),
+    layer: u32,
+    state: GameState,
+    timer: Timer,
+    clicks: Clicks,
+    /// Every click so far, for replays.
+    events: Vec<Click>,
+    /// Clicks of the replay being played back, and how many are done.
+    replay: Vec<Click>,
+    replay_next: usize,
+    /// Who played the replay.
+    replay_player: String,
+    /// Whether `grid` is a replay being watched rather than a game.
+    replaying: bool,
+    /// The grid's 3BV, worked out once per game.
+    bbbv: u32,
+    max_mines: u8,
+    topology: Topology,
+    tiling: Tiling,
+    depth: u32,
+    shape: Option<Mask>,
+    pack: PuzzlePack,
+    /// Index into `pack` while playing a puzzle.
+    puzzle: Option<usize>,
+    /// Whether the board was built by hand in the editor.
+    edited: bool,
+    solved: HashSet<String>,
+    editor: Editor,
+    /// The preset being played, if any.
+    difficulty: Option<Difficulty>,
+    /// Every finished game, oldest first.
+    history: Vec<Record>,
+    high_scores: HighScores,
+    /// The name being entered, kept for the next high score.
+    player_name: String,
+    /// Place in the high scores of the game just won.
+    high_score: Option<usize>,
+    /// Index into `high_scores.boards()` of the table being shown.
+    scores_board: usize,
+}
+
+impl Difficulty {
+    /// Returns the width, height and number of mines for this difficulty.
+    pub fn dimensions(&self) -> (u32, u32, u32) {
+        match *self {
+            Difficulty::Easy => (9, 9, 10),
+            Difficulty::Medium => (16, 16, 40),
+            Difficulty::Hard => (40, 16, 99),
+            Difficulty::Daily => (16, 16, 40),
+            //Difficulty::Custom(width, height, mines) => (width, height, mines),
+        }
+    }
+
+    /// A short lowercase name, as kept in files.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Daily => "daily",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Daily].iter()
+            .find(|d| d.name() == name)
+            .cloned()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            started: None,
+            stopped: None,
+            paused: None,
+            paused_for: Duration::from_secs(0),
+        }
+    }
+
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.resume();
+        if self.started.is_some() && self.stopped.is_none() {
+            self.stopped = Some(Instant::now());
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if self.is_running() {
+            self.paused = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused) = self.paused.take() {
+            self.paused_for += paused.elapsed();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some() && self.stopped.is_none() && self.paused.is_none()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        let end = match (self.started, self.paused.or(self.stopped)) {
+            (Some(_), Some(end)) => end,
+            (Some(_), None) => Instant::now(),
+            _ => return Duration::from_secs(0),
+        };
+        end - self.started.unwrap() - self.paused_for
+    }
+}
+
+impl Clicks {
+    pub fn total(&self) -> u32 {
+        self.left + self.right + self.chord
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+impl Game {
+    pub fn new(difficulty: Difficulty) -> Game {
+        let mut game = Game::with_grid(MineGrid::new(0, 0, 0));
+        game.reset(difficulty);
+        game
+    }
+
+    /// Starts a game on an existing grid.
+    pub fn with_grid(grid: MineGrid) -> Game {
+        Game {
+            cursor: first_cell(&grid),
+            bbbv: grid.bbbv(),
+            layout: grid.to_builder(),
+            opened: has_revealed(&grid),
+            retry: false,
+            grid,
+            endless: None,
+            endless_cursor: (0, 0),
+            layer: 0,
+            state: GameState::Play,
+            timer: Timer::new(),
+            clicks: Clicks::default(),
+            events: Vec::new(),
+            replay: Vec::new(),
+            replay_next: 0,
+            replay_player: String::new(),
+            replaying: false,
+            max_mines: 1,
+            topology: Topology::Bounded,
+            tiling: Tiling::Square,
+            depth: 1,
+            shape: None,
+            pack: PuzzlePack::builtin(),
+            puzzle: None,
+            edited: false,
+            solved: HashSet::new(),
+            editor: Editor::new(9, 9),
+            difficulty: None,
+            history: Vec::new(),
+            high_scores: HighScores::new(),
+            player_name: String::new(),
+            high_score: None,
+            scores_board: 0,
+        }
+    }
+
+    /// Starts a new game, keeping the player's settings. Each extra layer
+    /// adds another layer's worth of mines. With any setting changed from
+    /// the classic game, the board counts as a custom one.
+    pub fn reset(&mut self, difficulty: Difficulty) {
+        if difficulty == Difficulty::Daily {
+            return self.start_daily(today());
+        }
+        let (width, height, mines) = difficulty.dimensions();
+        let mut builder = MineGridBuilder::new(width, height, mines * self.depth);
+        if let Some(ref shape) = self.shape {
+            // Keep the difficulty's density of mines on the shape's cells.
+            let mines = mines as u64 * shape.count() as u64 / (width as u64 * height as u64);
+            builder = MineGridBuilder::new(0, 0, mines as u32 * self.depth)
+                .mask(shape.clone());
+        }
+        let grid = builder
+            .depth(self.depth)
+            .max_mines(self.max_mines)
+            .topology(self.topology)
+            .tiling(self.tiling)
+            .build();
+        self.start(grid);
+        let classic = self.shape.is_none() && self.depth == 1 && self.max_mines == 1 &&
+            self.topology == Topology::Bounded && self.tiling == Tiling::Square;
+        self.difficulty = if classic { Some(difficulty) } else { None };
+    }
+
+    /// Opens the first layer of a board in the editor, such as a layout read
+    /// from a file.
+    pub fn edit(&mut self, grid: &MineGrid) {
+        self.editor = Editor::from_grid(grid);
+        self.state = GameState::Edit;
+    }
+
+    /// Starts the daily board of `day`, counted in days since 1970 in UTC. The
+    /// player's settings are left out so everyone gets the same board. Only
+    /// the first game of the day is official, and later ones count as
+    /// retries. Leaving it unfinished once a cell is revealed loses it.
+    pub fn start_daily(&mut self, day: u64) {
+        let (width, height, mines) = Difficulty::Daily.dimensions();
+        self.start(MineGrid::with_seed(width, height, mines, day));
+        self.difficulty = Some(Difficulty::Daily);
+        self.retry = self.history.iter()
+            .any(|r| r.difficulty == Some(Difficulty::Daily) && r.seed == Some(day));
+    }
+
+    /// Starts the puzzle at `index` in the pack.
+    pub fn start_puzzle(&mut self, index: usize) {
+        let grid = match self.pack.get(index) {
+            Some(puzzle) => puzzle.grid(),
+            None => return,
+        };
+        self.start(grid);
+        self.puzzle = Some(index);
+    }
+
+    /// Starts playing on `grid`, keeping the "?" mark setting.
+    fn start(&mut self, mut grid: MineGrid) {
+        self.abandon();
+        grid.set_question_marks(self.grid.question_marks());
+
+        self.cursor = first_cell(&grid);
+        self.bbbv = grid.bbbv();
+        self.layout = grid.to_builder();
+        self.opened = has_revealed(&grid);
+        self.grid = grid;
+        self.retry = false;
+        self.layer = 0;
+        self.endless = None;
+        self.puzzle = None;
+        self.edited = false;
+        self.replaying = false;
+        self.difficulty = None;
+        self.high_score = None;
+        self.state = GameState::Play;
+        self.timer = Timer::new();
+        self.clicks = Clicks::default();
+        self.events.clear();
+    }
+
+    /// Starts the game just played over on the same board, keeping its
+    /// difficulty and puzzle.
+    pub fn retry(&mut self) {
+        if let Some(seed) = self.endless.as_ref().map(|endless| endless.seed()) {
+            self.reset_endless(seed);
+        } else {
+            let (difficulty, puzzle, edited) = (self.difficulty, self.puzzle, self.edited);
+            let grid = self.layout.clone().build();
+            self.start(grid);
+            self.difficulty = difficulty;
+            self.puzzle = puzzle;
+            self.edited = edited;
+        }
+        self.retry = true;
+    }
+
+    /// Whether this game replays a board already played.
+    pub fn is_retry(&self) -> bool {
+        self.retry
+    }
+
+    /// Plays back a recorded game from the start. Call `advance_replay` to
+    /// catch up with the clock.
+    pub fn start_replay(&mut self, replay: Replay) {
+        self.start(replay.grid);
+        self.replay = replay.clicks;
+        self.replay_next = 0;
+        self.replay_player = replay.player;
+        self.replaying = true;
+        self.state = GameState::Replay;
+        self.timer.start();
+    }
+
+    /// Makes the replay's clicks that are due by now.
+    pub fn advance_replay(&mut self) {
+        if self.state != GameState::Replay {
+            return;
+        }
+        let elapsed = self.timer.elapsed();
+        while let Some(&click) = self.replay.get(self.replay_next) {
+            if click.time > elapsed {
+                break;
+            }
+            match click.kind {
+                ClickKind::Left | ClickKind::Chord => self.grid.reveal(click.x, click.y),
+                ClickKind::Right => self.grid.toggle_flag(click.x, click.y),
+            }
+            self.cursor = (click.x, click.y);
+            self.replay_next += 1;
+        }
+        if self.replay_next == self.replay.len() || self.grid.state() != GridState::Play {
+            self.replay_next = self.replay.len();
+            self.timer.stop();
+        }
+    }
+
+    /// Clicks of the replay made so far, and in all.
+    pub fn replay_progress(&self) -> (usize, usize) {
+        (self.replay_next, self.replay.len())
+    }
+
+    /// Who played the replay, as given in the file. Can be empty.
+    pub fn replay_player(&self) -> &str {
+        &self.replay_player
+    }
+
+    /// The game so far as RAWVF text. `None` for endless games, puzzles and
+    /// boards the format can't hold, such as ones that start with cells
+    /// revealed.
+    pub fn export(&self) -> Option<String> {
+        if self.endless.is_some() || self.puzzle.is_some() || self.opened {
+            return None;
+        }
+        let player = match self.player_name.trim() {
+            "" => "Anonymous",
+            name => name,
+        };
+        replay::write(&self.grid, &self.events, player, self.timer.elapsed())
+    }
+
+    /// Starts an endless game on the board generated from `seed`.
+    pub fn reset_endless(&mut self, seed: u64) {
+        self.abandon();
+        let mut endless = InfiniteGrid::new(seed);
+        endless.set_question_marks(self.grid.question_marks());
+
+        self.endless = Some(endless);
+        self.endless_cursor = (0, 0);
+        self.retry = false;
+        self.puzzle = None;
+        self.replaying = false;
+        self.state = GameState::Play;
+        self.timer = Timer::new();
+        self.clicks = Clicks::default();
+        self.events.clear();
+    }
+
+    pub fn grid(&self) -> &MineGrid {
+        &self.grid
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn cursor(&self) -> (u32, u32) {
+        self.cursor
+    }
+
+    /// The endless board, while playing one.
+    pub fn endless(&self) -> Option<&InfiniteGrid> {
+        self.endless.as_ref()
+    }
+
+    pub fn endless_cursor(&self) -> (i64, i64) {
+        self.endless_cursor
+    }
+
+    /// The layer of the grid being shown and played on.
+    pub fn layer(&self) -> u32 {
+        self.layer
+    }
+
+    /// Mines per cell used for the next new game.
+    pub fn max_mines(&self) -> u8 {
+        self.max_mines
+    }
+
+    /// Topology used for the next new game.
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Tiling used for the next new game.
+    pub fn tiling(&self) -> Tiling {
+        self.tiling
+    }
+
+    /// Number of layers used for the next new game.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Board shape used for the next new game. `None` is a full rectangle.
+    pub fn shape(&self) -> Option<&Mask> {
+        self.shape.as_ref()
+    }
+
+    pub fn set_shape(&mut self, shape: Option<Mask>) {
+        self.shape = shape;
+    }
+
+    pub fn pack(&self) -> &PuzzlePack {
+        &self.pack
+    }
+
+    pub fn set_pack(&mut self, pack: PuzzlePack) {
+        self.pack = pack;
+        self.puzzle = None;
+    }
+
+    /// The puzzle being played, as an index into the pack.
+    pub fn puzzle(&self) -> Option<usize> {
+        self.puzzle
+    }
+
+    /// Names of the puzzles solved so far.
+    pub fn solved(&self) -> &HashSet<String> {
+        &self.solved
+    }
+
+    pub fn set_solved(&mut self, solved: HashSet<String>) {
+        self.solved = solved;
+    }
+
+    pub fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    /// The preset being played. `None` for custom boards and presets played
+    /// with other settings.
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.difficulty
+    }
+
+    /// Every finished game, oldest first. Endless games aren't kept.
+    pub fn history(&self) -> &[Record] {
+        &self.history
+    }
+
+    pub fn set_history(&mut self, history: Vec<Record>) {
+        self.history = history;
+    }
+
+    /// Days in a row the daily board was won, up to today.
+    pub fn daily_streak(&self) -> u32 {
+        history::daily_streak(&self.history, today())
+    }
+
+    pub fn high_scores(&self) -> &HighScores {
+        &self.high_scores
+    }
+
+    pub fn set_high_scores(&mut self, high_scores: HighScores) {
+        self.high_scores = high_scores;
+    }
+
+    pub fn player_name(&self) -> &str {
+        &self.player_name
+    }
+
+    /// Place in the high scores of the game just won, counting from 0.
+    pub fn high_score(&self) -> Option<usize> {
+        self.high_score
+    }
+
+    /// The high score table being shown.
+    pub fn scores_board(&self) -> Board {
+        let boards = self.high_scores.boards();
+        boards[self.scores_board % boards.len()]
+    }
+
+    /// The high score table the current game belongs in. Endless games,
+    /// puzzles and boards built in the editor have none.
+    pub fn board(&self) -> Option<Board> {
+        if self.endless.is_some() || self.puzzle.is_some() || self.edited {
+            return None;
+        }
+        Some(match self.difficulty {
+            Some(difficulty) => Board::Preset(difficulty),
+            None => Board::Custom(self.grid.width(), self.grid.height(), self.grid.mines()),
+        })
+    }
+
+    /// The first unsolved puzzle, starting from the current one. `None` once
+    /// they are all solved.
+    pub fn next_puzzle(&self) -> Option<usize> {
+        let len = self.pack.len();
+        let start = self.puzzle.unwrap_or(0);
+        (start..start + len)
+            .map(|i| i % len)
+            .find(|&i| !self.solved.contains(self.pack.get(i).unwrap().name()))
+    }
+
+    pub fn timer(&self) -> &Timer {
+        &self.timer
+    }
+
+    pub fn clicks(&self) -> Clicks {
+        self.clicks
+    }
+
+    /// The 3BV of the grid being played.
+    pub fn bbbv(&self) -> u32 {
+        self.bbbv
+    }
+
+    /// 3BV cleared per second. Games under a second count as taking one.
+    pub fn bbbv_per_second(&self) -> f64 {
+        self.bbbv as f64 / self.timer.elapsed().as_secs_f64().max(1.0)
+    }
+
+    /// 3BV over the clicks made. Perfect play scores 1, and flagging or
+    /// chording can beat it.
+    pub fn efficiency(&self) -> f64 {
+        match self.clicks.total() {
+            0 => 0.0,
+            clicks => self.bbbv as f64 / clicks as f64,
+        }
+    }
+
+    pub fn mines_left(&self) -> i32 {
+        self.grid.mines_left()
+    }
+
+    pub fn handle(&mut self, action: Action) {
+        match self.state {
+            GameState::Play => self.play_handle(action),
+            GameState::Lose | GameState::Win => self.end_handle(action),
+            GameState::New => self.new_handle(action),
+            GameState::Edit => self.edit_handle(action),
+            GameState::Stats => self.stats_handle(action),
+            GameState::Name => self.name_handle(action),
+            GameState::Scores => self.scores_handle(action),
+            GameState::Replay => self.end_handle(action),
+            GameState::Paused => self.paused_handle(action),
+            GameState::Quit => {},
+        }
+        if self.state == GameState::Quit {
+            self.abandon();
+        }
+    }
+
+    fn play_handle(&mut self, action: Action) {
+        if self.endless.is_some() {
+            return self.endless_handle(action);
+        }
+
+        match action {
+            Action::Reveal => {
+                self.timer.start();
+                match self.grid.get_cell_at(self.cursor.0, self.cursor.1, self.layer).map(|c| c.state()) {
+                    Some(CellState::Revealed) => self.click(ClickKind::Chord),
+                    Some(_) => self.click(ClickKind::Left),
+                    None => {},
+                }
+                self.grid.reveal_at(self.cursor.0, self.cursor.1, self.layer);
+                match self.grid.state() {
+                    GridState::Play => {},
+                    GridState::Win => {
+                        if let Some(puzzle) = self.puzzle.and_then(|i| self.pack.get(i)) {
+                            self.solved.insert(puzzle.name().to_string());
+                        }
+                        self.end(GameState::Win);
+                        let time = self.timer.elapsed();
+                        // Knowing the board beforehand doesn't make for a fair time.
+                        if !self.retry && self.board().is_some_and(|board| self.high_scores.qualifies(board, time)) {
+                            self.state = GameState::Name;
+                        }
+                    },
+                    GridState::Lose => self.end(GameState::Lose),
+                }
+            },
+            Action::Flag => {
+                self.click(ClickKind::Right);
+                self.grid.toggle_flag_at(self.cursor.0, self.cursor.1, self.layer);
+            },
+            Action::ToggleQuestionMarks => {
+                let enabled = self.grid.question_marks();
+                self.grid.set_question_marks(!enabled);
+            },
+            Action::Pause => self.pause(),
+            Action::MoveUp => self.move_cursor_up(),
+            Action::MoveDown => self.move_cursor_down(),
+            Action::MoveLeft => self.move_cursor_left(),
+            Action::MoveRight => self.move_cursor_right(),
+            Action::MoveBy(dx, dy) => {
+                for _ in 0..dx.abs().max(dy.abs()) {
+                    self.move_cursor(dx.signum() as i64, dy.signum() as i64);
+                }
+            },
+            Action::JumpUp => self.jump_cursor(0, -1),
+            Action::JumpDown => self.jump_cursor(0, 1),
+            Action::JumpLeft => self.jump_cursor(-1, 0),
+            Action::JumpRight => self.jump_cursor(1, 0),
+            Action::NextHidden => self.next_cell(|_, cell| cell.state() != CellState::Revealed),
+            Action::NextUnsatisfied => self.next_cell(is_unsatisfied),
+            Action::LayerUp => self.move_layer(1),
+            Action::LayerDown => self.move_layer(-1),
+            Action::NewGame => self.state = GameState::New,
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    fn endless_handle(&mut self, action: Action) {
+        let (x, y) = self.endless_cursor;
+        let endless = match self.endless {
+            Some(ref mut endless) => endless,
+            None => return,
+        };
+        match action {
+            Action::Reveal => {
+                self.timer.start();
+                endless.reveal(x, y);
+                if endless.state() == GridState::Lose {
+                    self.end(GameState::Lose);
+                }
+            },
+            Action::Flag => endless.toggle_flag(x, y),
+            Action::ToggleQuestionMarks => {
+                let enabled = endless.question_marks();
+                endless.set_question_marks(!enabled);
+            },
+            Action::Pause => self.pause(),
+            Action::MoveUp => self.endless_cursor.1 -= 1,
+            Action::MoveDown => self.endless_cursor.1 += 1,
+            Action::MoveLeft => self.endless_cursor.0 -= 1,
+            Action::MoveRight => self.endless_cursor.0 += 1,
+            Action::MoveBy(dx, dy) => {
+                self.endless_cursor.0 += dx as i64;
+                self.endless_cursor.1 += dy as i64;
+            },
+            Action::NewGame => self.state = GameState::New,
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    fn end_handle(&mut self, action: Action) {
+        match action {
+            Action::Retry if self.state == GameState::Lose => self.retry(),
+            Action::NewGame => self.state = GameState::New,
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    fn new_handle(&mut self, action: Action) {
+        match action {
+            Action::CycleMaxMines => self.max_mines = self.max_mines % MAX_MINES_PER_CELL + 1,
+            Action::ToggleTopology => self.topology = match self.topology {
+                Topology::Bounded => Topology::Torus,
+                Topology::Torus => Topology::Bounded,
+            },
+            Action::ToggleTiling => self.tiling = match self.tiling {
+                Tiling::Square => Tiling::Hex,
+                Tiling::Hex => Tiling::Square,
+            },
+            Action::CycleDepth => self.depth = self.depth % MAX_DEPTH + 1,
+            Action::Choose(difficulty) => self.reset(difficulty),
+            Action::Endless => self.reset_endless(time_seed()),
+            Action::Edit => self.state = GameState::Edit,
+            Action::Stats => self.state = GameState::Stats,
+            Action::Scores => {
+                // Start at the table for the game just played.
+                let boards = self.high_scores.boards();
+                self.scores_board = self.board()
+                    .and_then(|board| boards.iter().position(|&b| b == board))
+                    .unwrap_or(0);
+                self.state = GameState::Scores;
+            },
+            Action::Puzzle => {
+                // Once every puzzle is solved, go round again.
+                let next = self.puzzle.map_or(0, |i| i + 1) % self.pack.len().max(1);
+                self.start_puzzle(self.next_puzzle().unwrap_or(next));
+            },
+            Action::Cancel if self.replaying => self.state = GameState::Replay,
+            Action::Cancel => self.state = match self.endless.as_ref().map_or(self.grid.state(), |e| e.state()) {
+                GridState::Play => GameState::Play,
+                GridState::Lose => GameState::Lose,
+                GridState::Win => GameState::Win,
+            },
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    fn edit_handle(&mut self, action: Action) {
+        match action {
+            Action::ToggleMine => self.editor.toggle_mine(),
+            Action::ToggleOpened => self.editor.toggle_opened(),
+            Action::Resize(dw, dh) => self.editor.resize(dw, dh),
+            Action::MoveUp => self.editor.move_cursor(0, -1),
+            Action::MoveDown => self.editor.move_cursor(0, 1),
+            Action::MoveLeft => self.editor.move_cursor(-1, 0),
+            Action::MoveRight => self.editor.move_cursor(1, 0),
+            Action::PlayLayout => {
+                let grid = self.editor.build();
+                self.start(grid);
+                self.edited = true;
+            },
+            Action::NewGame => self.state = GameState::New,
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    fn stats_handle(&mut self, action: Action) {
+        match action {
+            Action::NewGame | Action::Cancel => self.state = GameState::New,
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    fn pause(&mut self) {
+        self.timer.pause();
+        self.state = GameState::Paused;
+    }
+
+    fn paused_handle(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.state = GameState::Quit,
+            _ => {
+                self.timer.resume();
+                self.state = GameState::Play;
+            },
+        }
+    }
+
+    /// Counts a click on the cell under the cursor.
+    fn click(&mut self, kind: ClickKind) {
+        match kind {
+            ClickKind::Left => self.clicks.left += 1,
+            ClickKind::Right => self.clicks.right += 1,
+            ClickKind::Chord => self.clicks.chord += 1,
+        }
+        self.events.push(Click {
+            time: self.timer.elapsed(),
+            kind,
+            x: self.cursor.0,
+            y: self.cursor.1,
+        });
+    }
+
+    fn name_handle(&mut self, action: Action) {
+        match action {
+            Action::Type(c) if !c.is_control() && self.player_name.chars().count() < MAX_NAME_LEN => {
+                self.player_name.push(c);
+            },
+            Action::Backspace => {
+                self.player_name.pop();
+            },
+            Action::Confirm => {
+                let name = self.player_name.trim().to_string();
+                if name.is_empty() {
+                    return;
+                }
+                if let Some(board) = self.board() {
+                    let score = Score { name, time: self.timer.elapsed() };
+                    self.high_score = self.high_scores.insert(board, score);
+                }
+                self.state = GameState::Win;
+            },
+            Action::Cancel => self.state = GameState::Win,
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    fn scores_handle(&mut self, action: Action) {
+        let len = self.high_scores.boards().len();
+        match action {
+            Action::MoveLeft => self.scores_board = (self.scores_board + len - 1) % len,
+            Action::MoveRight => self.scores_board = (self.scores_board + 1) % len,
+            Action::NewGame | Action::Cancel => self.state = GameState::New,
+            Action::Quit => self.state = GameState::Quit,
+            _ => {},
+        }
+    }
+
+    /// Counts an official daily game left after its first reveal as lost, so
+    /// a bad start can't be traded for another try.
+    fn abandon(&mut self) {
+        let started = self.clicks.left + self.clicks.chord > 0;
+        if self.difficulty == Some(Difficulty::Daily) && !self.retry && started &&
+            self.endless.is_none() && self.grid.state() == GridState::Play {
+            let state = self.state;
+            self.end(GameState::Lose);
+            self.state = state;
+        }
+    }
+
+    fn end(&mut self, state: GameState) {
+        self.timer.stop();
+        self.state = state;
+        if self.endless.is_none() {
+            self.history.push(Record {
+                difficulty: self.difficulty,
+                width: self.grid.width(),
+                height: self.grid.height(),
+                mines: self.grid.mines(),
+                seed: self.grid.seed(),
+                won: state == GameState::Win,
+                time: self.timer.elapsed(),
+                clicks: self.clicks.total(),
+                bbbv: self.bbbv,
+                retry: self.retry,
+            });
+        }
+    }
+
+    /// Moves the cursor, wrapping around the edges on a torus and jumping
+    /// over holes.
+    fn move_cursor(&mut self, dx: i64, dy: i64) {
+        let (mut x, mut y) = (self.cursor.0 as i64, self.cursor.1 as i64);
+        for _ in 0..self.grid.width().max(self.grid.height()) {
+            x += dx;
+            y += dy;
+            if let Some(point) = self.grid.wrap_point(x, y) {
+                self.cursor = point;
+                return;
+            }
+        }
+    }
+
+    /// Moves the cursor to the last cell of its row or column in the given
+    /// direction. Edges that wrap around still count as edges.
+    fn jump_cursor(&mut self, dx: i64, dy: i64) {
+        let (x, y) = self.cursor;
+        let line: Vec<(u32, u32)> = if dx != 0 {
+            (0..self.grid.width()).map(|x| (x, y)).collect()
+        } else {
+            (0..self.grid.height()).map(|y| (x, y)).collect()
+        };
+        let mut cells = line.into_iter().filter(|&(x, y)| self.grid.check_point_at(x, y, self.layer));
+        let point = if dx + dy < 0 { cells.next() } else { cells.next_back() };
+        if let Some(point) = point {
+            self.cursor = point;
+        }
+    }
+
+    /// Moves the cursor to the next cell on this layer that matches, in
+    /// reading order and going round to the start. Stays put if none do.
+    fn next_cell<F: Fn(&MineGrid, &Cell) -> bool>(&mut self, matches: F) {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let start = self.cursor.1 * width + self.cursor.0;
+        let len = width * height;
+        for i in (1..=len).map(|i| (start + i) % len) {
+            let (x, y) = (i % width, i / width);
+            if let Some(cell) = self.grid.get_cell_at(x, y, self.layer) {
+                if matches(&self.grid, &cell) {
+                    self.cursor = (x, y);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Switches to another layer, keeping the cursor where it is.
+    fn move_layer(&mut self, dz: i64) {
+        let (x, y) = (self.cursor.0 as i64, self.cursor.1 as i64);
+        if let Some((_, _, z)) = self.grid.wrap_point_at(x, y, self.layer as i64 + dz) {
+            self.layer = z;
+        }
+    }
+
+    fn move_cursor_up(&mut self) {
+        self.move_cursor(0, -1);
+    }
+
+    fn move_cursor_down(&mut self) {
+        self.move_cursor(0, 1);
+    }
+
+    fn move_cursor_left(&mut self) {
+        self.move_cursor(-1, 0);
+    }
+
+    fn move_cursor_right(&mut self) {
+        self.move_cursor(1, 0);
+    }
+}
+
+/// A seed that differs from game to game.
+fn time_seed() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    now.as_secs() ^ now.subsec_nanos() as u64
+}
+
+/// Days since 1970, in UTC.
+fn today() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / (24 * 60 * 60)
+}
+
+/// Whether a revealed number has fewer flags around it than mines.
+fn is_unsatisfied(grid: &MineGrid, cell: &Cell) -> bool {
+    if cell.state() != CellState::Revealed || cell.surrounding_mines() == 0 {
+        return false;
+    }
+    let flags: u8 = grid.get_neighbors_at(cell.x(), cell.y(), cell.z()).iter()
+        .map(|n| match n.state() {
+            CellState::Hidden(flags) => flags,
+            _ => 0,
+        })
+        .sum();
+    flags < cell.surrounding_mines()
+}
+
+/// The first cell in reading order, skipping holes.
+fn first_cell(grid: &MineGrid) -> (u32, u32) {
+    (0..grid.height())
+        .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+        .find(|&(x, y)| grid.check_point(x, y))
+        .unwrap_or((0, 0))
+}
+
+/// Whether any cell of the first layer is revealed.
+fn has_revealed(grid: &MineGrid) -> bool {
+    (0..grid.height())
+        .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+        .any(|(x, y)| grid.get_cell(x, y).is_some_and(|c| c.state() == CellState::Revealed))
+}
+
+#[cfg(test)]
+mod game_test {
+    use super::*;
+    use history::Stats;
+    use std::thread;
+
+    //  *2110
+    //  12*10
+    //  01121
+    //  0001*
+    const MINES: &[(u32, u32)] = &[(0, 0), (2, 1), (4, 3)];
+
+    fn new_game() -> Game {
+        Game::with_grid(MineGrid::with_mines(5, 4, MINES))
+    }
+
+    /// Reveals every safe cell.
+    fn win(game: &mut Game) {
+        for y in 0..4 {
+            for x in 0..5 {
+                if !MINES.contains(&(x, y)) {
+                    game.cursor = (x, y);
+                    game.handle(Action::Reveal);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let game = Game::new(Difficulty::Medium);
+
+        assert_eq!(16, game.grid().width());
+        assert_eq!(16, game.grid().height());
+        assert_eq!(40, game.grid().mines());
+        assert_eq!((0, 0), game.cursor());
+        assert_eq!(GameState::Play, game.state());
+        assert!(!game.timer().is_running());
+    }
+
+    #[test]
+    fn test_move_cursor() {
+        let mut game = new_game();
+
+        game.handle(Action::MoveUp);
+        game.handle(Action::MoveLeft);
+        assert_eq!((0, 0), game.cursor());
+
+        for _ in 0..10 {
+            game.handle(Action::MoveRight);
+            game.handle(Action::MoveDown);
+        }
+        assert_eq!((4, 3), game.cursor());
+    }
+
+    #[test]
+    fn test_jump_cursor() {
+        let mut game = new_game();
+
+        game.handle(Action::MoveBy(3, 5));
+        assert_eq!((3, 3), game.cursor());
+        game.handle(Action::MoveBy(-2, 0));
+        assert_eq!((1, 3), game.cursor());
+
+        game.handle(Action::JumpRight);
+        assert_eq!((4, 3), game.cursor());
+        game.handle(Action::JumpUp);
+        assert_eq!((4, 0), game.cursor());
+        game.handle(Action::JumpLeft);
+        game.handle(Action::JumpDown);
+        assert_eq!((0, 3), game.cursor());
+
+        // Edges that wrap around are still edges.
+        let mut torus = Game::with_grid(MineGridBuilder::new(5, 4, 0).topology(Topology::Torus).build());
+        torus.handle(Action::MoveBy(7, 0));
+        assert_eq!((2, 0), torus.cursor());
+        torus.handle(Action::JumpRight);
+        assert_eq!((4, 0), torus.cursor());
+    }
+
+    #[test]
+    fn test_next_cell() {
+        let mut game = new_game();
+        game.cursor = (0, 2);
+        game.handle(Action::Reveal);
+        // Reveals the opening in the bottom left.
+        assert_eq!(CellState::Revealed, game.grid().get_cell(2, 3).unwrap().state());
+
+        game.cursor = (0, 0);
+        game.handle(Action::NextHidden);
+        assert_eq!((1, 0), game.cursor());
+        game.cursor = (4, 3);
+        game.handle(Action::NextHidden);
+        assert_eq!((0, 0), game.cursor());
+
+        game.handle(Action::NextUnsatisfied);
+        assert_eq!((0, 1), game.cursor());
+        game.handle(Action::NextUnsatisfied);
+        assert_eq!((1, 1), game.cursor());
+
+        game.cursor = (0, 0);
+        game.handle(Action::Flag);
+        game.handle(Action::NextUnsatisfied);
+        assert_eq!((1, 1), game.cursor());
+    }
+
+    #[test]
+    fn test_timer() {
+        let mut game = new_game();
+
+        game.handle(Action::Flag);
+        assert!(!game.timer().is_running());
+
+        game.handle(Action::MoveRight);
+        game.handle(Action::Reveal);
+        assert!(game.timer().is_running());
+
+        game.handle(Action::MoveLeft);
+        game.handle(Action::Reveal);
+        assert!(game.timer().is_running());
+
+        game.handle(Action::Flag);
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        assert!(!game.timer().is_running());
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut game = new_game();
+        game.handle(Action::MoveRight);
+        game.handle(Action::Reveal);
+
+        game.handle(Action::Pause);
+        assert_eq!(GameState::Paused, game.state());
+        assert!(!game.timer().is_running());
+        let elapsed = game.timer().elapsed();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(elapsed, game.timer().elapsed());
+
+        // The action that ends the pause does nothing else.
+        game.handle(Action::MoveRight);
+        assert_eq!(GameState::Play, game.state());
+        assert_eq!((1, 0), game.cursor());
+        assert!(game.timer().is_running());
+        assert!(game.timer().elapsed() < elapsed + Duration::from_millis(20));
+
+        game.handle(Action::Pause);
+        game.handle(Action::Quit);
+        assert_eq!(GameState::Quit, game.state());
+    }
+
+    #[test]
+    fn test_mines_left() {
+        let mut game = new_game();
+        assert_eq!(3, game.mines_left());
+
+        for _ in 0..4 {
+            game.handle(Action::Flag);
+            game.handle(Action::MoveRight);
+        }
+        assert_eq!(-1, game.mines_left());
+    }
+
+    #[test]
+    fn test_clicks() {
+        let mut game = new_game();
+        assert_eq!(4, game.bbbv());
+
+        // Open the bottom left, flag the bottom right mine and chord the 1
+        // next to it.
+        game.handle(Action::MoveDown);
+        game.handle(Action::MoveDown);
+        game.handle(Action::Reveal);
+        game.handle(Action::MoveDown);
+        for _ in 0..4 {
+            game.handle(Action::MoveRight);
+        }
+        game.handle(Action::Flag);
+        game.handle(Action::MoveLeft);
+        game.handle(Action::Reveal);
+        assert_eq!(CellState::Revealed, game.grid().get_cell(4, 2).unwrap().state());
+
+        assert_eq!(Clicks { left: 1, right: 1, chord: 1 }, game.clicks());
+        assert_eq!(4.0 / 3.0, game.efficiency());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert_eq!(0, game.clicks().total());
+        assert_eq!(0.0, game.efficiency());
+    }
+
+    #[test]
+    fn test_new_game_menu() {
+        let mut game = new_game();
+
+        game.handle(Action::NewGame);
+        assert_eq!(GameState::New, game.state());
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::New, game.state());
+        game.handle(Action::Cancel);
+        assert_eq!(GameState::Play, game.state());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Choose(Difficulty::Hard));
+        assert_eq!(GameState::Play, game.state());
+        assert_eq!(40, game.grid().width());
+        assert_eq!(99, game.grid().mines());
+    }
+
+    #[test]
+    fn test_question_marks_survive_reset() {
+        let mut game = new_game();
+
+        game.handle(Action::ToggleQuestionMarks);
+        assert!(game.grid().question_marks());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert!(game.grid().question_marks());
+    }
+
+    #[test]
+    fn test_multimines() {
+        let mut game = new_game();
+        assert_eq!(1, game.max_mines());
+
+        game.handle(Action::CycleMaxMines);
+        assert_eq!(1, game.max_mines());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::CycleMaxMines);
+        assert_eq!(2, game.max_mines());
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert_eq!(2, game.grid().max_mines());
+
+        game.handle(Action::NewGame);
+        for _ in 0..MAX_MINES_PER_CELL - 1 {
+            game.handle(Action::CycleMaxMines);
+        }
+        assert_eq!(1, game.max_mines());
+    }
+
+    #[test]
+    fn test_torus() {
+        let mut game = new_game();
+
+        game.handle(Action::NewGame);
+        game.handle(Action::ToggleTopology);
+        assert_eq!(Topology::Torus, game.topology());
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert_eq!(Topology::Torus, game.grid().topology());
+
+        // The cursor wraps around too.
+        game.handle(Action::MoveLeft);
+        game.handle(Action::MoveUp);
+        assert_eq!((8, 8), game.cursor());
+        game.handle(Action::MoveRight);
+        assert_eq!((0, 8), game.cursor());
+    }
+
+    #[test]
+    fn test_hex() {
+        let mut game = new_game();
+
+        game.handle(Action::NewGame);
+        game.handle(Action::ToggleTiling);
+        assert_eq!(Tiling::Hex, game.tiling());
+        game.handle(Action::Choose(Difficulty::Medium));
+        assert_eq!(Tiling::Hex, game.grid().tiling());
+        assert_eq!(Topology::Bounded, game.grid().topology());
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut game = new_game();
+
+        game.handle(Action::NewGame);
+        game.handle(Action::CycleDepth);
+        game.handle(Action::CycleDepth);
+        assert_eq!(3, game.depth());
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert_eq!(3, game.grid().depth());
+        assert_eq!(30, game.grid().mines());
+
+        // The cursor keeps its place on the new layer.
+        game.handle(Action::MoveRight);
+        game.handle(Action::LayerDown);
+        assert_eq!(0, game.layer());
+        game.handle(Action::LayerUp);
+        game.handle(Action::LayerUp);
+        game.handle(Action::LayerUp);
+        assert_eq!(2, game.layer());
+        assert_eq!((1, 0), game.cursor());
+
+        game.handle(Action::Flag);
+        assert_eq!(CellState::Hidden(1), game.grid().get_cell_at(1, 0, 2).unwrap().state());
+        assert_eq!(CellState::Hidden(0), game.grid().get_cell_at(1, 0, 0).unwrap().state());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::CycleDepth);
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert_eq!(0, game.layer());
+        assert_eq!(1, game.grid().depth());
+    }
+
+    #[test]
+    fn test_shape() {
+        let grid = MineGridBuilder::new(0, 0, 0)
+            .mask(Mask::from_ascii("  #\n# #\n###"))
+            .build();
+        let mut game = Game::with_grid(grid);
+        assert_eq!((2, 0), game.cursor());
+
+        // The cursor jumps over holes, but not off the board.
+        game.handle(Action::MoveDown);
+        game.handle(Action::MoveLeft);
+        assert_eq!((0, 1), game.cursor());
+        game.handle(Action::MoveUp);
+        assert_eq!((0, 1), game.cursor());
+
+        let mut shape = Mask::new(9, 9);
+        shape.set(0, 0, false);
+        game.set_shape(Some(shape));
+        game.reset(Difficulty::Easy);
+        assert_eq!(9, game.grid().mines());
+        assert_eq!((1, 0), game.cursor());
+    }
+
+    #[test]
+    fn test_endless() {
+        let mut game = new_game();
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Endless);
+        assert_eq!(GameState::Play, game.state());
+        assert!(game.endless().is_some());
+
+        game.reset_endless(5);
+        game.handle(Action::Reveal);
+        assert!(game.timer().is_running());
+        assert!(game.endless().unwrap().score() > 0);
+
+        // Walk off to a mine and step on it.
+        let mine = (2..).find(|&x| game.endless().unwrap().get_cell(x, -1).mines() != 0).unwrap();
+        game.handle(Action::MoveUp);
+        for _ in 0..mine {
+            game.handle(Action::MoveRight);
+        }
+        assert_eq!((mine, -1), game.endless_cursor());
+        game.handle(Action::Flag);
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Play, game.state());
+        game.handle(Action::Flag);
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        assert!(!game.timer().is_running());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Cancel);
+        assert_eq!(GameState::Lose, game.state());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert!(game.endless().is_none());
+    }
+
+    #[test]
+    fn test_puzzles() {
+        let mut game = new_game();
+        game.set_pack(PuzzlePack::parse("One\no1*.\n\nTwo\n.*1o\n\nThree\no1*.\n").unwrap());
+        assert_eq!(Some(0), game.next_puzzle());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Puzzle);
+        assert_eq!(Some(0), game.puzzle());
+        assert_eq!(4, game.grid().width());
+        assert_eq!(CellState::Revealed, game.grid().get_cell(1, 0).unwrap().state());
+
+        // Losing leaves the puzzle unsolved.
+        for _ in 0..2 {
+            game.handle(Action::MoveRight);
+        }
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        assert_eq!(Some(0), game.next_puzzle());
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Puzzle);
+        assert_eq!(Some(0), game.puzzle());
+        for _ in 0..3 {
+            game.handle(Action::MoveRight);
+        }
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Win, game.state());
+        assert!(game.solved().contains("One"));
+        assert_eq!(Some(1), game.next_puzzle());
+
+        // Solved puzzles are skipped.
+        let solved = ["Two".to_string()].iter().cloned().collect();
+        game.set_solved(solved);
+        game.handle(Action::NewGame);
+        game.handle(Action::Puzzle);
+        assert_eq!(Some(0), game.puzzle());
+        game.handle(Action::NewGame);
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert_eq!(None, game.puzzle());
+    }
+
+    #[test]
+    fn test_editor() {
+        let mut game = new_game();
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Edit);
+        assert_eq!(GameState::Edit, game.state());
+        game.handle(Action::Resize(-5, -7));
+        game.handle(Action::MoveRight);
+        game.handle(Action::ToggleMine);
+        game.handle(Action::MoveDown);
+        game.handle(Action::MoveRight);
+        game.handle(Action::ToggleOpened);
+        assert_eq!(".*..\n..1.\n", game.editor().layout());
+
+        game.handle(Action::PlayLayout);
+        assert_eq!(GameState::Play, game.state());
+        assert_eq!(4, game.grid().width());
+        assert_eq!(1, game.grid().mines());
+
+        // The editor keeps its board for next time.
+        game.handle(Action::NewGame);
+        game.handle(Action::Edit);
+        assert_eq!(1, game.editor().grid().mines());
+
+        game.edit(&MineGrid::from_layout("o*.\n...\n").unwrap());
+        assert_eq!(GameState::Edit, game.state());
+        assert_eq!("1*.\n...\n", game.editor().layout());
+        game.handle(Action::Quit);
+        assert_eq!(GameState::Quit, game.state());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut game = new_game();
+
+        game.handle(Action::Reveal);
+        assert_eq!(1, game.history().len());
+        let record = &game.history()[0];
+        assert_eq!((None, 5, 4, 3), (record.difficulty, record.width, record.height, record.mines));
+        assert_eq!((None, false, 1, 4), (record.seed, record.won, record.clicks, record.bbbv));
+
+        game.handle(Action::NewGame);
+        game.handle(Action::Stats);
+        assert_eq!(GameState::Stats, game.state());
+        game.handle(Action::Cancel);
+        assert_eq!(GameState::New, game.state());
+
+        game.handle(Action::Choose(Difficulty::Hard));
+        assert_eq!(Some(Difficulty::Hard), game.difficulty());
+        assert!(game.grid().seed().is_some());
+        assert_eq!(Some(Difficulty::Hard), Difficulty::from_name(Difficulty::Hard.name()));
+
+        // Variants of a preset are kept with the custom boards.
+        game.handle(Action::NewGame);
+        game.handle(Action::CycleDepth);
+        game.handle(Action::Choose(Difficulty::Easy));
+        let (x, y, z) = (0..2 * 9 * 9).map(|i| (i % 9, i / 9 % 9, i / 81))
+            .find(|&(x, y, z)| game.grid().get_cell_at(x, y, z).unwrap().mines() != 0)
+            .unwrap();
+        game.cursor = (x, y);
+        game.layer = z;
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        assert_eq!(None, game.history()[1].difficulty);
+        assert_eq!(0, Stats::new(game.history(), Some(Difficulty::Easy)).played);
+        assert_eq!(2, Stats::new(game.history(), None).played);
+    }
+
+    #[test]
+    fn test_high_scores() {
+        let mut game = new_game();
+        let board = Board::Custom(5, 4, 3);
+        win(&mut game);
+
+        assert_eq!(GameState::Name, game.state());
+        for c in "Ann\tB".chars() {
+            game.handle(Action::Type(c));
+        }
+        game.handle(Action::Backspace);
+        game.handle(Action::Confirm);
+        assert_eq!(GameState::Win, game.state());
+        assert_eq!(Some(0), game.high_score());
+        assert_eq!("Ann", game.high_scores().table(board)[0].name);
+
+        // The table for the last game is shown first.
+        game.handle(Action::NewGame);
+        game.handle(Action::Scores);
+        assert_eq!(GameState::Scores, game.state());
+        assert_eq!(board, game.scores_board());
+        game.handle(Action::MoveRight);
+        assert_eq!(Board::Preset(Difficulty::Easy), game.scores_board());
+        game.handle(Action::Cancel);
+        assert_eq!(GameState::New, game.state());
+
+        // Skipping the name keeps the table as it is.
+        let mut game = Game::with_grid(MineGrid::with_mines(5, 4, MINES));
+        win(&mut game);
+        game.handle(Action::Cancel);
+        assert_eq!(GameState::Win, game.state());
+        assert!(game.high_scores().table(board).is_empty());
+    }
+
+    #[test]
+    fn test_variant_high_scores() {
+        for setting in &[Action::ToggleTopology, Action::ToggleTiling, Action::CycleMaxMines] {
+            let mut game = new_game();
+            game.handle(Action::NewGame);
+            game.handle(*setting);
+            game.handle(Action::Choose(Difficulty::Easy));
+            assert_eq!(None, game.difficulty());
+            assert_eq!(Some(Board::Custom(9, 9, 10)), game.board());
+
+            for y in 0..9 {
+                for x in 0..9 {
+                    if game.grid().get_cell(x, y).unwrap().mines() == 0 {
+                        game.cursor = (x, y);
+                        game.handle(Action::Reveal);
+                    }
+                }
+            }
+            assert_eq!(GameState::Name, game.state());
+            game.handle(Action::Type('A'));
+            game.handle(Action::Confirm);
+            assert!(game.high_scores().table(Board::Preset(Difficulty::Easy)).is_empty());
+            assert_eq!(1, game.high_scores().table(Board::Custom(9, 9, 10)).len());
+        }
+    }
+
+    #[test]
+    fn test_editor_high_scores() {
+        let mut game = new_game();
+        game.edit(&MineGrid::from_layout(".*\n..\n").unwrap());
+        game.handle(Action::PlayLayout);
+        assert_eq!(None, game.board());
+
+        for &(x, y) in &[(0, 0), (0, 1), (1, 1)] {
+            game.cursor = (x, y);
+            game.handle(Action::Reveal);
+        }
+        assert_eq!(GameState::Win, game.state());
+        assert!(game.high_scores().table(Board::Custom(2, 2, 1)).is_empty());
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut game = new_game();
+        game.handle(Action::MoveDown);
+        game.handle(Action::MoveDown);
+        game.handle(Action::Reveal);
+        game.handle(Action::MoveUp);
+        game.handle(Action::MoveUp);
+        game.handle(Action::Flag);
+        game.handle(Action::MoveRight);
+        game.handle(Action::MoveDown);
+        game.handle(Action::Reveal);
+        assert_eq!(3, game.events.len());
+        let text = game.export().unwrap();
+
+        let mut replay = Game::with_grid(MineGrid::new(9, 9, 10));
+        replay.start_replay(replay::parse(&text).unwrap());
+        assert_eq!(GameState::Replay, replay.state());
+        assert_eq!("Anonymous", replay.replay_player());
+        assert_eq!((0, 3), replay.replay_progress());
+        replay.advance_replay();
+        assert_eq!((3, 3), replay.replay_progress());
+        assert!(!replay.timer().is_running());
+        for y in 0..4 {
+            for x in 0..5 {
+                assert_eq!(game.grid().get_cell(x, y).unwrap().state(),
+                           replay.grid().get_cell(x, y).unwrap().state());
+            }
+        }
+
+        replay.handle(Action::NewGame);
+        assert_eq!(GameState::New, replay.state());
+        replay.handle(Action::Cancel);
+        assert_eq!(GameState::Replay, replay.state());
+
+        // Cells revealed from the start can't be written down.
+        let mut game = Game::with_grid(MineGrid::from_layout("o.*\n...\n").unwrap());
+        game.cursor = (2, 1);
+        game.handle(Action::Reveal);
+        assert!(game.export().is_none());
+        game.set_pack(PuzzlePack::parse("Easy\n...\n.*.\n").unwrap());
+        game.start_puzzle(0);
+        game.handle(Action::Reveal);
+        assert!(game.export().is_none());
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut game = new_game();
+        game.handle(Action::MoveDown);
+        game.handle(Action::MoveDown);
+        game.handle(Action::Reveal);
+        game.cursor = (0, 0);
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+
+        game.handle(Action::Retry);
+        assert_eq!(GameState::Play, game.state());
+        assert!(game.is_retry());
+        assert_eq!(0, game.clicks().total());
+        assert_eq!(CellState::Hidden(0), game.grid().get_cell(0, 2).unwrap().state());
+        assert_eq!(1, game.grid().get_cell(2, 1).unwrap().mines());
+
+        // Retries are kept in the history but stay out of the high scores.
+        win(&mut game);
+        assert_eq!(GameState::Win, game.state());
+        assert!(game.history()[1].retry);
+        assert!(!game.history()[0].retry);
+        game.handle(Action::Retry);
+        assert_eq!(GameState::Win, game.state());
+
+        // Puzzles start over with their cells revealed again.
+        game.start_puzzle(0);
+        game.cursor = (1, 0);
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        game.handle(Action::Retry);
+        assert_eq!(Some(0), game.puzzle());
+        assert_eq!(CellState::Revealed, game.grid().get_cell(2, 0).unwrap().state());
+        assert_eq!(CellState::Hidden(0), game.grid().get_cell(1, 0).unwrap().state());
+
+        // Endless boards start over from the same seed.
+        game.reset_endless(7);
+        game.endless.as_mut().unwrap().reveal(0, 0);
+        game.retry();
+        assert_eq!(7, game.endless().unwrap().seed());
+        assert_eq!(0, game.endless().unwrap().score());
+    }
+
+    #[test]
+    fn test_daily() {
+        let mut game = new_game();
+        game.handle(Action::NewGame);
+        game.handle(Action::CycleMaxMines);
+        game.handle(Action::Choose(Difficulty::Daily));
+        assert_eq!(Some(Difficulty::Daily), game.difficulty());
+        assert_eq!(1, game.grid().max_mines());
+
+        game.start_daily(100);
+        let mut other = Game::new(Difficulty::Easy);
+        other.start_daily(100);
+        assert_eq!(Some(100), game.grid().seed());
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(game.grid().get_cell(x, y).unwrap().mines(),
+                           other.grid().get_cell(x, y).unwrap().mines());
+            }
+        }
+        assert!(!game.is_retry());
+
+        // Once the day's board is finished, playing it again is practice.
+        let (x, y) = (0..16 * 16).map(|i| (i % 16, i / 16))
+            .find(|&(x, y)| game.grid().get_cell(x, y).unwrap().mines() != 0)
+            .unwrap();
+        game.cursor = (x, y);
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        assert_eq!(Some(Difficulty::Daily), game.history()[0].difficulty);
+        game.start_daily(100);
+        assert!(game.is_retry());
+        game.start_daily(101);
+        assert!(!game.is_retry());
+    }
+
+    #[test]
+    fn test_daily_abandoned() {
+        let mut game = new_game();
+        let safe_cell = |game: &Game| (0..16 * 16).map(|i| (i % 16, i / 16))
+            .find(|&(x, y)| game.grid().get_cell(x, y).unwrap().mines() == 0)
+            .unwrap();
+
+        // Looking at the board without revealing anything costs nothing.
+        game.start_daily(100);
+        game.start_daily(100);
+        assert!(!game.is_retry());
+        assert!(game.history().is_empty());
+
+        // Once a cell is revealed, starting over loses the day.
+        game.cursor = safe_cell(&game);
+        game.handle(Action::Reveal);
+        game.handle(Action::NewGame);
+        game.handle(Action::Choose(Difficulty::Easy));
+        assert_eq!(1, game.history().len());
+        assert!(!game.history()[0].won);
+        game.start_daily(100);
+        assert!(game.is_retry());
+
+        // And so does quitting.
+        game.start_daily(101);
+        game.cursor = safe_cell(&game);
+        game.handle(Action::Reveal);
+        game.handle(Action::Pause);
+        game.handle(Action::Quit);
+        assert_eq!(GameState::Quit, game.state());
+        assert_eq!(2, game.history().len());
+        assert_eq!((Some(101), false), (game.history()[1].seed, game.history()[1].won));
+    }
+
+    #[test]
+    fn test_quit() {
+        let mut game = new_game();
+
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        game.handle(Action::Reveal);
+        assert_eq!(GameState::Lose, game.state());
+        game.handle(Action::Quit);
+        assert_eq!(GameState::Quit, game.state());
+    }
+}
@@ -0,0 +1,166 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rustbox::{
+    self,
+    Color,
+    Event,
+    EventResult,
+    Key,
+    Style,
+};
+
+use rustbox_cell::Cell;
+use terminal::Terminal;
+
+const BLANK_CELL: Cell = Cell {
+    ch: ' ',
+    style: rustbox::RB_NORMAL,
+    fg: Color::Default,
+    bg: Color::Default,
+};
+
+/// An in-memory terminal that records what was last presented and replays
+/// scripted input events.
+pub struct HeadlessTerminal {
+    width: usize,
+    height: usize,
+    back: RefCell<Vec<Cell>>,
+    front: RefCell<Vec<Cell>>,
+    cursor: RefCell<(isize, isize)>,
+    events: RefCell<VecDeque<Event>>,
+}
+
+impl HeadlessTerminal {
+    pub fn new(width: usize, height: usize) -> Self {
+        HeadlessTerminal {
+            width,
+            height,
+            back: RefCell::new(vec![BLANK_CELL; width * height]),
+            front: RefCell::new(vec![BLANK_CELL; width * height]),
+            cursor: RefCell::new((-1, -1)),
+            events: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    pub fn push_key(&self, key: Key) {
+        self.push_event(Event::KeyEvent(key));
+    }
+
+    pub fn push_event(&self, event: Event) {
+        self.events.borrow_mut().push_back(event);
+    }
+
+    /// Dumps the presented buffer as blocks of characters, foreground colors,
+    /// background colors and styles (B: bold, U: underline, W: both, R:
+    /// reverse), one line per screen row. Trailing blank cells and rows are
+    /// trimmed.
+    pub fn snapshot(&self) -> String {
+        let front = self.front.borrow();
+        let rows: Vec<&[Cell]> = front.chunks(self.width)
+            .map(|row| {
+                let len = row.iter().rposition(|c| !is_blank(c)).map_or(0, |i| i + 1);
+                &row[..len]
+            })
+            .collect();
+        let rows = match rows.iter().rposition(|row| !row.is_empty()) {
+            Some(last) => &rows[..last + 1],
+            None => &rows[..0],
+        };
+
+        let mut out = String::new();
+        push_block(&mut out, "chars", rows, |c| c.ch);
+        push_block(&mut out, "fg", rows, |c| color_char(c.fg));
+        push_block(&mut out, "bg", rows, |c| color_char(c.bg));
+        push_block(&mut out, "style", rows, |c| style_char(c.style));
+        let (x, y) = *self.cursor.borrow();
+        out.push_str(&format!("cursor: ({}, {})\n", x, y));
+        out
+    }
+
+    fn put(&self, x: usize, y: usize, cell: Cell) {
+        if x < self.width && y < self.height {
+            self.back.borrow_mut()[y * self.width + x] = cell;
+        }
+    }
+}
+
+impl Terminal for HeadlessTerminal {
+    fn clear(&self) {
+        for cell in self.back.borrow_mut().iter_mut() {
+            *cell = BLANK_CELL;
+        }
+    }
+
+    fn present(&self) {
+        self.front.borrow_mut().clone_from_slice(&self.back.borrow());
+    }
+
+    fn set_cursor(&self, x: isize, y: isize) {
+        *self.cursor.borrow_mut() = (x, y);
+    }
+
+    fn print(&self, x: usize, y: usize, style: Style, fg: Color, bg: Color, s: &str) {
+        for (i, ch) in s.chars().enumerate() {
+            self.print_char(x + i, y, style, fg, bg, ch);
+        }
+    }
+
+    fn print_char(&self, x: usize, y: usize, style: Style, fg: Color, bg: Color, ch: char) {
+        self.put(x, y, Cell { ch, style, fg, bg });
+    }
+
+    fn poll_event(&self, _raw: bool) -> EventResult {
+        Ok(self.events.borrow_mut().pop_front().unwrap_or(Event::NoEvent))
+    }
+
+    fn peek_event(&self, _timeout: Duration, raw: bool) -> EventResult {
+        self.poll_event(raw)
+    }
+}
+
+fn push_block(out: &mut String, name: &str, rows: &[&[Cell]], f: fn(&Cell) -> char) {
+    out.push_str(name);
+    out.push_str(":\n");
+    for row in rows {
+        out.extend(row.iter().map(f));
+        out.push('\n');
+    }
+}
+
+fn is_blank(cell: &Cell) -> bool {
+    cell.ch == ' ' && cell.style == rustbox::RB_NORMAL &&
+        cell.fg == Color::Default && cell.bg == Color::Default
+}
+
+pub fn color_char(color: Color) -> char {
+    match color {
+        Color::Default => '.',
+        Color::Black => 'k',
+        Color::Red => 'r',
+        Color::Green => 'g',
+        Color::Yellow => 'y',
+        Color::Blue => 'b',
+        Color::Magenta => 'm',
+        Color::Cyan => 'c',
+        Color::White => 'w',
+        _ => '?',
+    }
+}
+
+fn style_char(style: Style) -> char {
+    if style == rustbox::RB_NORMAL {
+        '.'
+    } else if style == rustbox::RB_BOLD {
+        'B'
+    } else if style == rustbox::RB_UNDERLINE {
+        'U'
+    } else if style == rustbox::RB_BOLD | rustbox::RB_UNDERLINE {
+        'W'
+    } else if style == rustbox::RB_REVERSE {
+        'R'
+    } else {
+        '?'
+    }
+}
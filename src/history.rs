@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use game::Difficulty;
+
+/// Upper bounds, in seconds, of the game lengths grouped together in
+/// `Stats::lengths`. Longer games go in one last group.
+pub const LENGTH_BOUNDS: &[u64] = &[30, 60, 120, 300];
+
+/// A finished game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// `None` for custom boards, presets played with other settings, puzzles
+    /// and boards from the editor.
+    pub difficulty: Option<Difficulty>,
+    pub width: u32,
+    pub height: u32,
+    pub mines: u32,
+    /// `None` when the mines were placed by hand.
+    pub seed: Option<u64>,
+    pub won: bool,
+    pub time: Duration,
+    pub clicks: u32,
+    pub bbbv: u32,
+    /// Whether the board had been played before.
+    pub retry: bool,
+}
+
+/// Totals over the records of one difficulty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub played: u32,
+    pub won: u32,
+    /// Wins in a row at the end of the history.
+    pub streak: u32,
+    pub best_streak: u32,
+    /// The fastest win on a board not played before.
+    pub best_time: Option<Duration>,
+    pub retries: u32,
+    /// How many games fell under each of `LENGTH_BOUNDS`, then how many took
+    /// longer.
+    pub lengths: Vec<u32>,
+}
+
+impl Record {
+    /// Reads a record from a line written by `to_line`. Lines from before
+    /// retries were kept have no last field.
+    pub fn parse(line: &str) -> Option<Record> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let retry = match fields.get(9) {
+            None if fields.len() == 9 => false,
+            Some(&"new") => false,
+            Some(&"retry") => true,
+            _ => return None,
+        };
+        let difficulty = match fields[0] {
+            "custom" => None,
+            name => Some(Difficulty::from_name(name)?),
+        };
+        let seed = match fields[4] {
+            "-" => None,
+            seed => Some(seed.parse().ok()?),
+        };
+        let won = match fields[5] {
+            "win" => true,
+            "lose" => false,
+            _ => return None,
+        };
+        Some(Record {
+            difficulty,
+            width: fields[1].parse().ok()?,
+            height: fields[2].parse().ok()?,
+            mines: fields[3].parse().ok()?,
+            seed,
+            won,
+            time: Duration::from_millis(fields[6].parse().ok()?),
+            clicks: fields[7].parse().ok()?,
+            bbbv: fields[8].parse().ok()?,
+            retry,
+        })
+    }
+
+    /// Writes the record as one tab separated line, without the newline.
+    pub fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                self.difficulty.map_or("custom", |d| d.name()),
+                self.width, self.height, self.mines,
+                self.seed.map_or("-".to_string(), |seed| seed.to_string()),
+                if self.won { "win" } else { "lose" },
+                self.time.as_millis(), self.clicks, self.bbbv,
+                if self.retry { "retry" } else { "new" })
+    }
+}
+
+impl Stats {
+    /// Works out the stats of the records with the given difficulty, in the
+    /// order they were played.
+    pub fn new(records: &[Record], difficulty: Option<Difficulty>) -> Stats {
+        let mut stats = Stats {
+            lengths: vec![0; LENGTH_BOUNDS.len() + 1],
+            ..Stats::default()
+        };
+        for record in records.iter().filter(|r| r.difficulty == difficulty) {
+            stats.played += 1;
+            if record.retry {
+                stats.retries += 1;
+            }
+            if record.won {
+                stats.won += 1;
+                stats.streak += 1;
+                stats.best_streak = stats.best_streak.max(stats.streak);
+                if !record.retry && stats.best_time.is_none_or(|best| record.time < best) {
+                    stats.best_time = Some(record.time);
+                }
+            } else {
+                stats.streak = 0;
+            }
+            let secs = record.time.as_secs();
+            let group = LENGTH_BOUNDS.iter().position(|&bound| secs < bound)
+                .unwrap_or(LENGTH_BOUNDS.len());
+            stats.lengths[group] += 1;
+        }
+        stats
+    }
+
+    /// Share of games won, from 0 to 1.
+    pub fn win_rate(&self) -> f64 {
+        match self.played {
+            0 => 0.0,
+            played => self.won as f64 / played as f64,
+        }
+    }
+}
+
+/// Days in a row up to `day` whose daily board was won on the official
+/// attempt. Until the day's board is played, the streak up to the day before
+/// still counts.
+pub fn daily_streak(records: &[Record], day: u64) -> u32 {
+    // Whether each day's official attempt was won.
+    let mut official = HashMap::new();
+    for record in records.iter().filter(|r| r.difficulty == Some(Difficulty::Daily) && !r.retry) {
+        if let Some(seed) = record.seed {
+            official.entry(seed).or_insert(record.won);
+        }
+    }
+
+    let mut day = match official.get(&day) {
+        Some(&true) => Some(day),
+        Some(&false) => return 0,
+        None => day.checked_sub(1),
+    };
+    let mut streak = 0;
+    while let Some(d) = day.filter(|d| official.get(d) == Some(&true)) {
+        streak += 1;
+        day = d.checked_sub(1);
+    }
+    streak
+}
+
+/// Reads the history, oldest game first. A missing file means no games yet,
+/// and lines that can't be read are skipped.
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<Record>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text.lines().filter_map(Record::parse).collect()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Adds a game to the end of the history.
+pub fn append<P: AsRef<Path>>(path: P, record: &Record) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", record.to_line())
+}
+
+#[cfg(test)]
+mod history_test {
+    use super::*;
+
+    fn record(difficulty: Option<Difficulty>, won: bool, secs: u64) -> Record {
+        Record {
+            difficulty,
+            width: 9,
+            height: 9,
+            mines: 10,
+            seed: Some(42),
+            won,
+            time: Duration::from_secs(secs),
+            clicks: 20,
+            bbbv: 15,
+            retry: false,
+        }
+    }
+
+    #[test]
+    fn test_line() {
+        let easy = record(Some(Difficulty::Easy), true, 12);
+        assert_eq!("easy\t9\t9\t10\t42\twin\t12000\t20\t15\tnew", easy.to_line());
+        assert_eq!(Some(easy.clone()), Record::parse(&easy.to_line()));
+        assert_eq!(Some(easy.clone()), Record::parse("easy\t9\t9\t10\t42\twin\t12000\t20\t15"));
+
+        let retry = Record { retry: true, ..easy };
+        assert_eq!(Some(retry.clone()), Record::parse(&retry.to_line()));
+
+        let custom = Record { seed: None, ..record(None, false, 3) };
+        assert_eq!(Some(custom.clone()), Record::parse(&custom.to_line()));
+
+        assert_eq!(None, Record::parse("easy\t9\t9\t10\t42\tdraw\t12000\t20\t15"));
+        assert_eq!(None, Record::parse("extreme\t9\t9\t10\t42\twin\t12000\t20\t15"));
+        assert_eq!(None, Record::parse(""));
+    }
+
+    #[test]
+    fn test_stats() {
+        let easy = Some(Difficulty::Easy);
+        let records = [
+            record(easy, true, 40),
+            record(easy, true, 25),
+            record(None, false, 5),
+            record(easy, false, 400),
+            record(easy, true, 70),
+            Record { retry: true, ..record(easy, true, 10) },
+        ];
+
+        let stats = Stats::new(&records, easy);
+        assert_eq!(5, stats.played);
+        assert_eq!(4, stats.won);
+        assert_eq!(1, stats.retries);
+        assert_eq!(2, stats.streak);
+        assert_eq!(2, stats.best_streak);
+        assert_eq!(Some(Duration::from_secs(25)), stats.best_time);
+        assert_eq!(vec![2, 1, 1, 0, 1], stats.lengths);
+        assert_eq!(0.8, stats.win_rate());
+
+        let stats = Stats::new(&records, Some(Difficulty::Hard));
+        assert_eq!(0, stats.played);
+        assert_eq!(None, stats.best_time);
+        assert_eq!(0.0, stats.win_rate());
+    }
+
+    #[test]
+    fn test_daily_streak() {
+        let daily = |day: u64, won: bool, retry: bool| {
+            Record { seed: Some(day), retry, ..record(Some(Difficulty::Daily), won, 60) }
+        };
+        let records = [
+            daily(8, true, false),
+            daily(10, false, false),
+            daily(10, true, true),
+            daily(11, true, false),
+            record(Some(Difficulty::Easy), false, 5),
+            daily(12, true, false),
+            daily(12, false, true),
+        ];
+
+        assert_eq!(2, daily_streak(&records, 12));
+        // Today's board hasn't been played yet.
+        assert_eq!(2, daily_streak(&records, 13));
+        assert_eq!(0, daily_streak(&records, 14));
+        // A retry doesn't make up for a lost official attempt.
+        assert_eq!(0, daily_streak(&records, 10));
+        assert_eq!(1, daily_streak(&records, 9));
+        assert_eq!(0, daily_streak(&[], 0));
+    }
+}
@@ -0,0 +1,9 @@
+extern crate minegrid;
+
+pub mod editor;
+pub mod game;
+pub mod history;
+pub mod puzzle;
+pub mod replay;
+pub mod scores;
+pub mod storage;
@@ -1,7 +1,15 @@
-extern crate rand;
+extern crate minegrid;
+extern crate rust_mines;
 extern crate rustbox;
 
+use std::collections::VecDeque;
 use std::default::Default;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
 
 use rustbox::{
     Color,
@@ -10,11 +18,21 @@ use rustbox::{
     RustBox,
 };
 
+use minegrid::{CellState, EndView, MineGrid, Tiling, Topology};
+use minegrid::infinite::InfiniteGrid;
+use minegrid::layout;
+use minegrid::mask::Mask;
+use rust_mines::game::{Action, Difficulty, Game, GameState, MAX_NAME_LEN};
+use rust_mines::history::{self, LENGTH_BOUNDS, Stats};
+use rust_mines::scores::{self, Board};
+use rust_mines::{puzzle, replay, storage};
 use rustbox_cell::{Cell, print_cell_repeated_x, print_cell_repeated_y};
-use minegrid::{CellState, GridState, MineGrid};
+use terminal::Terminal;
 
-mod minegrid;
+#[cfg(test)]
+mod headless;
 mod rustbox_cell;
+mod terminal;
 
 
 const BORDER_CELL: Cell = Cell {
@@ -29,12 +47,30 @@ const FLAG_CELL: Cell = Cell {
     fg: Color::Red,
     bg: Color::Blue,
 };
+const QUESTION_CELL: Cell = Cell {
+    ch: '?',
+    style: rustbox::RB_BOLD,
+    fg: Color::White,
+    bg: Color::Blue,
+};
 const MINE_CELL: Cell = Cell {
     ch: '*',
     style: rustbox::RB_BOLD,
     fg: Color::Black,
     bg: Color::Red,
 };
+const MISSED_MINE_CELL: Cell = Cell {
+    ch: '*',
+    style: rustbox::RB_BOLD,
+    fg: Color::Black,
+    bg: Color::White,
+};
+const WRONG_FLAG_CELL: Cell = Cell {
+    ch: 'X',
+    style: rustbox::RB_BOLD,
+    fg: Color::Red,
+    bg: Color::White,
+};
 const HIDDEN_CELL: Cell = Cell {
     ch: ' ',
     style: rustbox::RB_NORMAL,
@@ -49,39 +85,34 @@ const REVEALED_CELL: Cell = Cell {
 };
 
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum GameState {
-    Play,
-    Lose,
-    Win,
-    New,
-    Quit,
-}
-
-enum Difficulty {
-    Easy,
-    Medium,
-    Hard,
-    //Custom(u32, u32, u32),
-}
-
 static ACTION_STRINGS: &[&[&str]] = &[
     // GameState::Play
     &[
         "Space: reveal",
         "f: flag",
+        "?: toggle ? marks",
         "Arrow keys: move",
+        "PgUp/PgDn: layer",
+        "H/J/K/L: move 5",
+        "Home/End: row edge",
+        "g/G: col edge",
+        "Tab: next hidden",
+        "u: next number",
+        "p: pause",
         "",
         "n: new game",
         "q: quit",
     ],
     // GameState::Lose
     &[
+        "r: retry",
+        "x: export replay",
         "n: new game",
         "q: quit",
     ],
     // GameState::Win
     &[
+        "x: export replay",
         "n: new game",
         "q: quit",
     ],
@@ -90,12 +121,72 @@ static ACTION_STRINGS: &[&[&str]] = &[
         "e: easy",
         "m: medium",
         "h: hard",
-        "",
+        "y: daily challenge",
+        "i: endless",
+        "p: puzzles",
+        "b: board editor",
+        "s: statistics",
+        "r: high scores",
+        "x: mines per cell",
+        "w: wrap edges",
+        "t: hex tiles",
+        "d: layers",
         "c: cancel",
         "q: quit",
     ],
+    // GameState::Edit
+    &[
+        "Space: toggle mine",
+        "o: start revealed",
+        "Arrow keys: move",
+        "+/-: width",
+        "]/[: height",
+        "s: save",
+        "p: play it",
+        "",
+        "n: new game",
+        "q: quit",
+    ],
+    // GameState::Stats
+    &[
+        "c: back",
+        "q: quit",
+    ],
+    // GameState::Name
+    &[
+        "Enter: save",
+        "Esc: skip",
+    ],
+    // GameState::Scores
+    &[
+        "Left/Right: board",
+        "c: back",
+        "q: quit",
+    ],
+    // GameState::Replay
+    &[
+        "n: new game",
+        "q: quit",
+    ],
+    // GameState::Paused
+    &[
+        "Any key: resume",
+        "q: quit",
+    ],
 ];
 
+/// Shown before the name typed in for a high score.
+const NAME_PROMPT: &str = "Name: ";
+
+/// Name of the files the board editor saves to, in the current directory.
+const LAYOUT_NAME: &str = "layout";
+
+/// Where finished games are exported to, in the current directory.
+const REPLAY_FILE: &str = "replay.rawvf";
+
+/// How many cells the fast moves go.
+const FAST_MOVE: i32 = 5;
+
 fn format_mine_cell(mines: u8) -> Cell {
     let (ch, fg, bg) = match mines {
         1 => ('1', Color::Blue, Color::Default),
@@ -106,29 +197,96 @@ fn format_mine_cell(mines: u8) -> Cell {
         6 => ('6', Color::Cyan, Color::Default),
         7 => ('7', Color::White, Color::Cyan),
         8 => ('8', Color::White, Color::Magenta),
-        _ => panic!("Unexpected number of surrounding mines: {}!", mines),
+        // Only reachable with multiple mines per cell.
+        9 => ('9', Color::White, Color::Red),
+        _ => ('+', Color::White, Color::Red),
     };
 
     Cell {
-        ch: ch,
+        ch,
         style: rustbox::RB_NORMAL,
-        fg: fg,
-        bg: bg,
+        fg,
+        bg,
     }
 }
 
+/// Flags are drawn as "F", or as the flag count when a cell has several.
+fn format_flag_cell(flags: u8) -> Cell {
+    let ch = match flags {
+        1 => 'F',
+        _ => std::char::from_digit(flags as u32, 10).unwrap_or('+'),
+    };
+
+    Cell {
+        ch,
+        ..FLAG_CELL
+    }
+}
+
+/// In the editor every cell shows what it holds. Cells that don't start out
+/// revealed keep the hidden background.
+fn format_editor_cell(cell: &minegrid::Cell, opened: bool) -> Cell {
+    let cell = if cell.mines() != 0 {
+        MINE_CELL
+    } else if cell.surrounding_mines() != 0 {
+        format_mine_cell(cell.surrounding_mines())
+    } else {
+        REVEALED_CELL
+    };
+    if opened || cell.bg != Color::Default {
+        cell
+    } else {
+        Cell {
+            bg: HIDDEN_CELL.bg,
+            ..cell
+        }
+    }
+}
+
+fn format_cell(state: CellState, mines: u8, surrounding_mines: u8) -> Cell {
+    match state {
+        CellState::Hidden(0) => HIDDEN_CELL,
+        CellState::Hidden(flags) => format_flag_cell(flags),
+        CellState::Question => QUESTION_CELL,
+        CellState::Revealed => if mines != 0 {
+            MINE_CELL
+        } else if surrounding_mines != 0 {
+            format_mine_cell(surrounding_mines)
+        } else {
+            REVEALED_CELL
+        },
+    }
+}
+
+fn format_grid_cell(cell: &minegrid::Cell) -> Cell {
+    format_cell(cell.state(), cell.mines(), cell.surrounding_mines())
+}
+
+/// On a lost board, the mines left and the wrong flags are uncovered too.
+fn format_end_cell(cell: &minegrid::Cell, view: EndView) -> Cell {
+    match view {
+        EndView::Unchanged => format_grid_cell(cell),
+        EndView::Mine => MISSED_MINE_CELL,
+        EndView::Exploded => MINE_CELL,
+        EndView::WrongFlag => WRONG_FLAG_CELL,
+    }
+}
+
+/// Renders the cells of one layer of the grid.
 struct CellRenderer<'a> {
     grid: &'a MineGrid,
     x: u32,
     y: u32,
+    z: u32,
 }
 
 impl<'a> CellRenderer<'a> {
-    fn new(grid: &'a MineGrid) -> Self {
+    fn new(grid: &'a MineGrid, z: u32) -> Self {
         CellRenderer {
-            grid: grid,
+            grid,
             x: 0,
             y: 0,
+            z,
         }
     }
 }
@@ -137,189 +295,321 @@ impl<'a> Iterator for CellRenderer<'a> {
     type Item = (u32, u32, Cell);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.y >= self.grid.height() {
-            return None;
+        while self.y < self.grid.height() {
+            let (x, y) = (self.x, self.y);
+            if self.x + 1 < self.grid.width() {
+                self.x += 1;
+            } else {
+                self.x = 0;
+                self.y += 1;
+            }
+
+            // Holes are left blank.
+            if let (Some(cell), Some(view)) =
+                (self.grid.get_cell_at(x, y, self.z), self.grid.end_view_at(x, y, self.z)) {
+                return Some((x, y, format_end_cell(&cell, view)));
+            }
         }
+        None
+    }
+}
 
-        let cell = match self.grid.get_cell(self.x, self.y) {
-            Some(c) => c,
-            None => panic!("CellRenderer: Could not get cell at ({}, {})!", self.x, self.y),
-        };
-        let cell = match cell.state() {
-            CellState::Hidden(0) => HIDDEN_CELL,
-            CellState::Hidden(_) => FLAG_CELL,
-            CellState::Revealed => if cell.mines() != 0 {
-                MINE_CELL
-            } else if cell.surrounding_mines() != 0 {
-                format_mine_cell(cell.surrounding_mines())
-            } else {
-                REVEALED_CELL
-            },
-        };
-        let item = (self.x, self.y, cell);
+/// The part of an endless board in view. Scrolls to keep the cursor a few
+/// cells away from the edges of the view.
+struct Camera {
+    origin: (i64, i64),
+    size: (u32, u32),
+}
 
-        if self.x + 1 < self.grid.width() {
-            self.x += 1;
-        } else {
-            self.x = 0;
-            self.y += 1;
+impl Camera {
+    const MARGIN: i64 = 3;
+
+    /// A view centered on (0, 0).
+    fn new(size: (u32, u32)) -> Camera {
+        Camera {
+            origin: (-(size.0 as i64) / 2, -(size.1 as i64) / 2),
+            size,
         }
+    }
 
-        Some(item)
+    fn follow(&mut self, cursor: (i64, i64)) {
+        fn follow_axis(origin: i64, size: u32, pos: i64) -> i64 {
+            let margin = Camera::MARGIN.min((size as i64 - 1) / 2);
+            if pos < origin + margin {
+                pos - margin
+            } else if pos > origin + size as i64 - 1 - margin {
+                pos - (size as i64 - 1 - margin)
+            } else {
+                origin
+            }
+        }
+        self.origin = (follow_axis(self.origin.0, self.size.0, cursor.0),
+                       follow_axis(self.origin.1, self.size.1, cursor.1));
     }
+
+    /// Iterates over the cells in view as view coordinates and the formatted
+    /// cell there.
+    fn render<'a>(&'a self, grid: &'a InfiniteGrid) -> impl Iterator<Item = (u32, u32, Cell)> + 'a {
+        let (width, height) = self.size;
+        (0..height).flat_map(move |y| (0..width).map(move |x| {
+            let cell = grid.get_cell(self.origin.0 + x as i64, self.origin.1 + y as i64);
+            (x, y, format_cell(cell.state(), cell.mines(), cell.surrounding_mines()))
+        }))
+    }
+}
+
+/// Maps a key press to a game action for the current screen.
+fn key_action(state: GameState, key: Key) -> Option<Action> {
+    let action = match (state, key) {
+        (GameState::Play, Key::Char(' ')) => Action::Reveal,
+        (GameState::Play, Key::Char('f')) => Action::Flag,
+        (GameState::Play, Key::Char('?')) => Action::ToggleQuestionMarks,
+        (GameState::Play, Key::Up) => Action::MoveUp,
+        (GameState::Play, Key::Down) => Action::MoveDown,
+        (GameState::Play, Key::Left) => Action::MoveLeft,
+        (GameState::Play, Key::Right) => Action::MoveRight,
+        // Shift with the arrow keys can't be told apart, so the vi keys
+        // stand in for it.
+        (GameState::Play, Key::Char('K')) => Action::MoveBy(0, -FAST_MOVE),
+        (GameState::Play, Key::Char('J')) => Action::MoveBy(0, FAST_MOVE),
+        (GameState::Play, Key::Char('H')) => Action::MoveBy(-FAST_MOVE, 0),
+        (GameState::Play, Key::Char('L')) => Action::MoveBy(FAST_MOVE, 0),
+        (GameState::Play, Key::Home) => Action::JumpLeft,
+        (GameState::Play, Key::End) => Action::JumpRight,
+        // The page keys switch layers, so vi's g and G jump along columns.
+        (GameState::Play, Key::Char('g')) => Action::JumpUp,
+        (GameState::Play, Key::Char('G')) => Action::JumpDown,
+        (GameState::Play, Key::Tab) => Action::NextHidden,
+        (GameState::Play, Key::Char('u')) => Action::NextUnsatisfied,
+        (GameState::Play, Key::PageUp) => Action::LayerUp,
+        (GameState::Play, Key::PageDown) => Action::LayerDown,
+        (GameState::Play, Key::Char('p')) => Action::Pause,
+        (GameState::Paused, Key::Char('q')) => Action::Quit,
+        (GameState::Paused, _) => Action::Resume,
+        (GameState::Play, Key::Char('n')) |
+        (GameState::Lose, Key::Char('n')) |
+        (GameState::Win, Key::Char('n')) |
+        (GameState::Replay, Key::Char('n')) => Action::NewGame,
+        (GameState::Lose, Key::Char('r')) => Action::Retry,
+        (GameState::Lose, Key::Char('x')) |
+        (GameState::Win, Key::Char('x')) => Action::Export,
+        (GameState::New, Key::Char('e')) => Action::Choose(Difficulty::Easy),
+        (GameState::New, Key::Char('m')) => Action::Choose(Difficulty::Medium),
+        (GameState::New, Key::Char('h')) => Action::Choose(Difficulty::Hard),
+        (GameState::New, Key::Char('y')) => Action::Choose(Difficulty::Daily),
+        (GameState::New, Key::Char('i')) => Action::Endless,
+        (GameState::New, Key::Char('p')) => Action::Puzzle,
+        (GameState::New, Key::Char('b')) => Action::Edit,
+        (GameState::New, Key::Char('s')) => Action::Stats,
+        (GameState::Stats, Key::Char('c')) => Action::Cancel,
+        (GameState::New, Key::Char('r')) => Action::Scores,
+        (GameState::Scores, Key::Left) => Action::MoveLeft,
+        (GameState::Scores, Key::Right) => Action::MoveRight,
+        (GameState::Scores, Key::Char('c')) => Action::Cancel,
+        (GameState::Name, Key::Char(c)) => Action::Type(c),
+        (GameState::Name, Key::Backspace) => Action::Backspace,
+        (GameState::Name, Key::Enter) => Action::Confirm,
+        (GameState::Name, Key::Esc) => Action::Cancel,
+        (GameState::Edit, Key::Char(' ')) => Action::ToggleMine,
+        (GameState::Edit, Key::Char('o')) => Action::ToggleOpened,
+        (GameState::Edit, Key::Up) => Action::MoveUp,
+        (GameState::Edit, Key::Down) => Action::MoveDown,
+        (GameState::Edit, Key::Left) => Action::MoveLeft,
+        (GameState::Edit, Key::Right) => Action::MoveRight,
+        (GameState::Edit, Key::Char('+')) |
+        (GameState::Edit, Key::Char('=')) => Action::Resize(1, 0),
+        (GameState::Edit, Key::Char('-')) => Action::Resize(-1, 0),
+        (GameState::Edit, Key::Char(']')) => Action::Resize(0, 1),
+        (GameState::Edit, Key::Char('[')) => Action::Resize(0, -1),
+        (GameState::Edit, Key::Char('s')) => Action::Save,
+        (GameState::Edit, Key::Char('p')) => Action::PlayLayout,
+        (GameState::Edit, Key::Char('n')) => Action::NewGame,
+        (GameState::New, Key::Char('x')) => Action::CycleMaxMines,
+        (GameState::New, Key::Char('w')) => Action::ToggleTopology,
+        (GameState::New, Key::Char('t')) => Action::ToggleTiling,
+        (GameState::New, Key::Char('d')) => Action::CycleDepth,
+        (GameState::New, Key::Char('c')) => Action::Cancel,
+        (_, Key::Char('q')) => Action::Quit,
+        _ => return None,
+    };
+    Some(action)
 }
 
-struct Game {
-    rb: RustBox,
-    grid: MineGrid,
+struct Ui<T: Terminal> {
+    rb: T,
+    game: Game,
     grid_pos: (usize, usize),
     actions_pos: (usize, usize),
-    status_pos: (usize, usize),
-    mines_pos: (usize, usize),
-    cursor_pos: (usize, usize),
-    //grid_changed: bool,
-    state: GameState,
+    camera: Camera,
+    /// Shown under the status until the next key press.
+    message: Option<String>,
+    /// Where the editor saves: the layout it opened or last saved to.
+    layout_path: Option<PathBuf>,
+    /// Events read ahead while looking for a focus report.
+    pending: VecDeque<Event>,
 }
 
-impl Game {
-    fn new(rb: RustBox) -> Game {
-        let mut game = Game {
-            rb: rb,
-            grid: MineGrid::new(0, 0, 0),
+impl<T: Terminal> Ui<T> {
+    fn new(rb: T, game: Game) -> Ui<T> {
+        Ui {
+            rb,
+            game,
             grid_pos: (20, 1),
             actions_pos: (0, 2),
-            status_pos: (0, 0),
-            mines_pos: (0, 0),
-            cursor_pos: (0, 0),
-            //grid_changed: false,
-            state: GameState::Play,
-        };
-
-        game.reset(Difficulty::Easy);
-
-        game
+            camera: Camera::new((40, 16)),
+            message: None,
+            layout_path: None,
+            pending: VecDeque::new(),
+        }
     }
 
-    fn reset(&mut self, difficulty: Difficulty) {
-        match difficulty {
-            Difficulty::Easy => self.grid = MineGrid::new(9, 9, 10),
-            Difficulty::Medium => self.grid = MineGrid::new(16, 16, 40),
-            Difficulty::Hard => self.grid = MineGrid::new(40, 16, 99),
-            //Difficulty::Custom(width, height, mines) =>
-            //    self.grid = MineGrid::new(width, height, mines),
+    /// The grid on screen: the one being played, or the editor's preview.
+    fn shown_grid(&self) -> &MineGrid {
+        match self.game.state() {
+            GameState::Edit => self.game.editor().grid(),
+            _ => self.game.grid(),
         }
-
-        self.status_pos = (0, self.grid_pos.1 + self.grid.height() as usize + 3);
-        self.mines_pos = (self.grid_pos.0 + self.grid.width() as usize / 2, 0);
-        self.cursor_pos = (0, 0);
-        self.state = GameState::Play;
     }
 
-    fn move_cursor_up(&mut self) {
-        if self.cursor_pos.1 > 0 {
-            self.cursor_pos.1 -= 1;
+    /// Size of the grid on screen: the whole grid, or the camera's view of an
+    /// endless board.
+    fn grid_size(&self) -> (u32, u32) {
+        let grid = self.shown_grid();
+        match self.game.endless() {
+            Some(_) => self.camera.size,
+            None => (grid.width(), grid.height()),
         }
     }
 
-    fn move_cursor_down(&mut self) {
-        if self.cursor_pos.1 < self.grid.height() as usize - 1 {
-            self.cursor_pos.1 += 1;
+    fn tiling(&self) -> Tiling {
+        match self.game.endless() {
+            Some(_) => Tiling::Square,
+            None => self.shown_grid().tiling(),
         }
     }
 
-    fn move_cursor_left(&mut self) {
-        if self.cursor_pos.0 > 0 {
-            self.cursor_pos.0 -= 1;
-        }
+    fn status_pos(&self) -> (usize, usize) {
+        // Below both the grid and the longest list of actions.
+        let actions_len = ACTION_STRINGS.iter().map(|a| a.len()).max().unwrap_or(0);
+        let y = (self.grid_pos.1 + self.grid_size().1 as usize + 3)
+            .max(self.actions_pos.1 + actions_len + 1);
+        (0, y)
     }
 
-    fn move_cursor_right(&mut self) {
-        if self.cursor_pos.0 < self.grid.width() as usize - 1 {
-            self.cursor_pos.0 += 1;
+    fn mines_pos(&self) -> (usize, usize) {
+        (self.grid_pos.0 + self.grid_inner_width() / 2, 0)
+    }
+
+    /// Columns between the left and right borders. Hex cells are spaced out
+    /// so that odd rows can sit between the cells of even rows.
+    fn grid_inner_width(&self) -> usize {
+        let width = self.grid_size().0 as usize;
+        match self.tiling() {
+            Tiling::Square => width,
+            Tiling::Hex => width * 2,
         }
     }
 
+    /// Screen position of the cell at (x, y). Takes signed coordinates so
+    /// wrap hints can be placed just outside the grid.
+    fn cell_pos(&self, x: i64, y: i64) -> (usize, usize) {
+        let col = match self.tiling() {
+            Tiling::Square => x,
+            Tiling::Hex => 2 * x + (y & 1),
+        };
+        ((self.grid_pos.0 as i64 + col + 1) as usize,
+         (self.grid_pos.1 as i64 + y + 1) as usize)
+    }
+
     fn update(&mut self) {
-        match self.state {
-            GameState::Play => self.play_update(),
-            GameState::Lose => self.lose_update(),
-            GameState::Win => self.win_update(),
-            GameState::New => self.new_update(),
-            GameState::Quit => {},
-        }
-    }
-
-    fn play_update(&mut self) {
-        match self.rb.poll_event(false).unwrap() {
-            Event::KeyEvent(key) => {
-                match key {
-                    Key::Char(' ') => {
-                        self.grid.reveal(self.cursor_pos.0 as u32,
-                                         self.cursor_pos.1 as u32);
-                        match self.grid.state() {
-                            GridState::Play => {},
-                            GridState::Win => self.state = GameState::Win,
-                            GridState::Lose => self.state = GameState::Lose,
-                        }
-                    },
-                    Key::Char('f') => self.grid.toggle_flag(
-                        self.cursor_pos.0 as u32, self.cursor_pos.1 as u32),
-                    Key::Up => self.move_cursor_up(),
-                    Key::Down => self.move_cursor_down(),
-                    Key::Left => self.move_cursor_left(),
-                    Key::Right => self.move_cursor_right(),
-                    Key::Char('n') => self.state = GameState::New,
-                    Key::Char('q') => self.state = GameState::Quit,
-                    _ => return,
-                }
+        // Wake up every second while the clock runs so the timer redraws, and
+        // more often to keep a replay on time.
+        let event = if let Some(event) = self.pending.pop_front() {
+            event
+        } else if self.game.state() == GameState::Replay && self.game.timer().is_running() {
+            self.rb.peek_event(Duration::from_millis(50), false).unwrap()
+        } else if self.game.timer().is_running() {
+            self.rb.peek_event(Duration::from_secs(1), false).unwrap()
+        } else {
+            self.rb.poll_event(false).unwrap()
+        };
+        let key = match event {
+            Event::KeyEvent(Key::Esc) => match self.read_focus_report() {
+                Some(false) => {
+                    self.pause_play();
+                    None
+                },
+                Some(true) => None,
+                None => Some(Key::Esc),
+            },
+            Event::KeyEvent(key) => Some(key),
+            // The player may have looked away while rearranging windows.
+            Event::ResizeEvent(..) => {
+                self.pause_play();
+                None
             },
-            _ => return,
+            _ => None,
+        };
+        if let Some(key) = key {
+            self.message = None;
+            if let Some(action) = key_action(self.game.state(), key) {
+                if action == Action::Save {
+                    let path = self.layout_path.clone().unwrap_or_else(|| free_layout_path(Path::new("")));
+                    self.message = Some(match fs::write(&path, self.game.editor().layout()) {
+                        Ok(()) => format!("Saved to {}", path.display()),
+                        Err(e) => format!("Could not save: {}", e),
+                    });
+                    self.layout_path = Some(path);
+                }
+                if action == Action::Export {
+                    self.message = Some(match self.game.export() {
+                        Some(text) => match fs::write(REPLAY_FILE, text) {
+                            Ok(()) => format!("Saved to {}", REPLAY_FILE),
+                            Err(e) => format!("Could not save: {}", e),
+                        },
+                        None => "This board can't be exported".to_string(),
+                    });
+                }
+                self.game.handle(action);
+                if action == Action::Endless {
+                    self.camera = Camera::new(self.camera.size);
+                }
+                self.camera.follow(self.game.endless_cursor());
+            }
         }
+        self.game.advance_replay();
     }
 
-    fn lose_update(&mut self) {
-        match self.rb.poll_event(false).unwrap() {
-            Event::KeyEvent(key) => {
-                match key {
-                    Key::Char('n') => self.state = GameState::New,
-                    Key::Char('q') => self.state = GameState::Quit,
-                    _ => return,
-                }
+    /// Reads the rest of a focus report after an Esc. Terminals send
+    /// `ESC [ I` on gaining focus and `ESC [ O` on losing it, which termbox
+    /// passes on as Esc and two characters. Returns whether focus was gained,
+    /// or `None` if the Esc was a key press. Events read meanwhile are kept
+    /// for the next updates.
+    fn read_focus_report(&mut self) -> Option<bool> {
+        // The rest of a report arrives along with the Esc.
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            match self.rb.peek_event(Duration::from_millis(10), false) {
+                Ok(Event::NoEvent) | Err(_) => break,
+                Ok(event) => events.push(event),
+            }
+            if !matches!(events[0], Event::KeyEvent(Key::Char('['))) {
+                break;
+            }
+        }
+        match events[..] {
+            [Event::KeyEvent(Key::Char('[')), Event::KeyEvent(Key::Char('I'))] => Some(true),
+            [Event::KeyEvent(Key::Char('[')), Event::KeyEvent(Key::Char('O'))] => Some(false),
+            _ => {
+                self.pending.extend(events);
+                None
             },
-            _ => return,
         }
     }
 
-    fn win_update(&mut self) {
-        match self.rb.poll_event(false).unwrap() {
-            Event::KeyEvent(key) => {
-                match key {
-                    Key::Char('n') => self.state = GameState::New,
-                    Key::Char('q') => self.state = GameState::Quit,
-                    _ => return,
-                }
-            },
-            _ => return,
-        }
-    }
-
-    fn new_update(&mut self) {
-        match self.rb.poll_event(false).unwrap() {
-            Event::KeyEvent(key) => {
-                match key {
-                    Key::Char('e') => self.reset(Difficulty::Easy),
-                    Key::Char('m') => self.reset(Difficulty::Medium),
-                    Key::Char('h') => self.reset(Difficulty::Hard),
-                    Key::Char('c') => self.state = match self.grid.state() {
-                        GridState::Play => GameState::Play,
-                        GridState::Lose => GameState::Lose,
-                        GridState::Win => GameState::Win,
-                    },
-                    Key::Char('q') => self.state = GameState::Quit,
-                    _ => return,
-                }
-            },
-            _ => return,
+    fn pause_play(&mut self) {
+        if self.game.state() == GameState::Play {
+            self.game.handle(Action::Pause);
         }
     }
 
@@ -331,18 +621,43 @@ impl Game {
 
         self.draw_actions();
 
-        // Mine counter
-        self.rb.print(self.mines_pos.0, self.mines_pos.1,
-                      rustbox::RB_BOLD, Color::Red, Color::White,
-                      &format!("{:02}", self.grid.mines_left()));
+        // Mine counter. Endless boards have no mine count to count down from,
+        // and the editor counts the mines placed.
+        let mines = match self.game.state() {
+            GameState::Edit => Some(self.game.editor().grid().mines() as i32),
+            GameState::Stats | GameState::Scores => None,
+            _ if self.game.endless().is_some() => None,
+            _ => Some(self.game.mines_left()),
+        };
+        if let Some(mines) = mines {
+            let mines_pos = self.mines_pos();
+            self.rb.print(mines_pos.0, mines_pos.1,
+                          rustbox::RB_BOLD, Color::Red, Color::White,
+                          &format!("{:02}", mines));
+        }
 
         self.draw_grid();
 
         self.draw_status();
 
-        if self.state == GameState::Play {
-            self.rb.set_cursor((self.cursor_pos.0 + self.grid_pos.0 + 1) as isize,
-                               (self.cursor_pos.1 + self.grid_pos.1 + 1) as isize);
+        if self.game.state() == GameState::Edit {
+            let cursor = self.game.editor().cursor();
+            let (x, y) = self.cell_pos(cursor.0 as i64, cursor.1 as i64);
+            self.rb.set_cursor(x as isize, y as isize);
+        } else if self.game.state() == GameState::Play || self.game.state() == GameState::Replay {
+            let cursor = match self.game.endless() {
+                Some(_) => {
+                    let (x, y) = self.game.endless_cursor();
+                    (x - self.camera.origin.0, y - self.camera.origin.1)
+                },
+                None => (self.game.cursor().0 as i64, self.game.cursor().1 as i64),
+            };
+            let (x, y) = self.cell_pos(cursor.0, cursor.1);
+            self.rb.set_cursor(x as isize, y as isize);
+        } else if self.game.state() == GameState::Name {
+            // At the end of the name, on the line under the status.
+            let x = self.grid_pos.0 + NAME_PROMPT.len() + self.game.player_name().chars().count();
+            self.rb.set_cursor(x as isize, self.status_pos().1 as isize + 1);
         } else {
             self.rb.set_cursor(-1, -1);
         }
@@ -351,53 +666,1324 @@ impl Game {
     }
 
     fn draw_grid(&self) {
+        let grid = self.game.grid();
+
+        if self.game.state() == GameState::Stats {
+            return self.draw_stats();
+        }
+        if self.game.state() == GameState::Paused {
+            // Nothing to study while the clock is stopped.
+            return self.draw_border();
+        }
+        if self.game.state() == GameState::Scores {
+            return self.draw_scores();
+        }
+
+        if self.game.state() == GameState::Edit {
+            let editor = self.game.editor();
+            self.draw_border();
+            for (x, y, _) in CellRenderer::new(editor.grid(), 0) {
+                let cell = format_editor_cell(&editor.grid().get_cell(x, y).unwrap(), editor.is_opened(x, y));
+                let (x, y) = self.cell_pos(x as i64, y as i64);
+                self.rb.print_char(x, y, cell.style, cell.fg, cell.bg, cell.ch);
+            }
+            return;
+        }
+
+        if let Some(endless) = self.game.endless() {
+            self.draw_border();
+            for (x, y, cell) in self.camera.render(endless) {
+                let (x, y) = self.cell_pos(x as i64, y as i64);
+                self.rb.print_char(x, y, cell.style, cell.fg, cell.bg, cell.ch);
+            }
+            return;
+        }
+
+        if grid.topology() == Topology::Torus {
+            self.draw_wrap_hints();
+        } else if grid.mask().is_full() {
+            self.draw_border();
+        } else {
+            self.draw_outline();
+        }
+
+        // Draw the grid using a CellRenderer.
+        for (x, y, cell) in CellRenderer::new(grid, self.game.layer()) {
+            let (x, y) = self.cell_pos(x as i64, y as i64);
+            self.rb.print_char(x, y, cell.style, cell.fg, cell.bg, cell.ch);
+        }
+
+    }
+
+    fn draw_border(&self) {
+        let height = self.grid_size().1 as usize;
+
         // Draw the top border.
-        print_cell_repeated_x(&self.rb, self.grid_pos.0, self.grid_pos.1, BORDER_CELL, self.grid.width() as usize + 2);
+        print_cell_repeated_x(&self.rb, self.grid_pos.0, self.grid_pos.1, BORDER_CELL, self.grid_inner_width() + 2);
 
         // Draw the bottom border.
-        print_cell_repeated_x(&self.rb, self.grid_pos.0, self.grid_pos.1 + self.grid.height() as usize + 1, BORDER_CELL, self.grid.width() as usize + 2);
+        print_cell_repeated_x(&self.rb, self.grid_pos.0, self.grid_pos.1 + height + 1, BORDER_CELL, self.grid_inner_width() + 2);
 
         // Draw the left border.
-        print_cell_repeated_y(&self.rb, self.grid_pos.0, self.grid_pos.1 + 1, BORDER_CELL, self.grid.height() as usize);
+        print_cell_repeated_y(&self.rb, self.grid_pos.0, self.grid_pos.1 + 1, BORDER_CELL, height);
 
         // Draw the right border.
-        print_cell_repeated_y(&self.rb, self.grid_pos.0 + self.grid.width() as usize + 1, self.grid_pos.1 + 1, BORDER_CELL, self.grid.height() as usize);
+        print_cell_repeated_y(&self.rb, self.grid_pos.0 + self.grid_inner_width() + 1, self.grid_pos.1 + 1, BORDER_CELL, height);
+    }
 
-        // Draw the grid using a CellRenderer.
-        for (x, y, cell) in CellRenderer::new(&self.grid) {
-            let (x, y) = (x as usize, y as usize);
-            self.rb.print_char(self.grid_pos.0 + x + 1, self.grid_pos.1 + y + 1, cell.style, cell.fg, cell.bg, cell.ch);
+    /// Borders an irregular grid, around its outline and any holes.
+    fn draw_outline(&self) {
+        let grid = self.game.grid();
+        let (width, height) = (self.grid_inner_width() as i64, grid.height() as i64);
+
+        // Mark the columns each cell covers. Hex cells also cover the gap on
+        // one side, so cells in a row aren't walled off from each other.
+        let mut covered = vec![false; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..grid.width() as i64 {
+                if !grid.check_point(x as u32, y as u32) {
+                    continue;
+                }
+                let cols = match grid.tiling() {
+                    Tiling::Square => x..x + 1,
+                    Tiling::Hex => 2 * x..2 * x + 2,
+                };
+                for col in cols {
+                    covered[(y * width + col) as usize] = true;
+                }
+            }
         }
+        let is_covered = |x: i64, y: i64| {
+            x >= 0 && y >= 0 && x < width && y < height && covered[(y * width + x) as usize]
+        };
 
+        for y in -1..height + 1 {
+            for x in -1..width + 1 {
+                let near = (-1..2).any(|j| (-1..2).any(|i| is_covered(x + i, y + j)));
+                if near && !is_covered(x, y) {
+                    let (sx, sy) = ((self.grid_pos.0 as i64 + x + 1) as usize,
+                                    (self.grid_pos.1 as i64 + y + 1) as usize);
+                    self.rb.print_char(sx, sy, BORDER_CELL.style, BORDER_CELL.fg, BORDER_CELL.bg, BORDER_CELL.ch);
+                }
+            }
+        }
+    }
+
+    /// On a torus, the border shows the cells from the opposite edge,
+    /// underlined to tell them apart from the grid itself.
+    fn draw_wrap_hints(&self) {
+        let grid = self.game.grid();
+        let (width, height) = (grid.width() as i64, grid.height() as i64);
+
+        for y in -1..height + 1 {
+            for x in -1..width + 1 {
+                let on_border = x < 0 || y < 0 || x == width || y == height;
+                if !on_border {
+                    continue;
+                }
+                let (wx, wy) = match grid.wrap_point(x, y) {
+                    Some(point) => point,
+                    None => continue,
+                };
+                let cell = format_grid_cell(&grid.get_cell_at(wx, wy, self.game.layer()).unwrap());
+                let (sx, sy) = self.cell_pos(x, y);
+                self.rb.print_char(sx, sy, cell.style | rustbox::RB_UNDERLINE, cell.fg, cell.bg, cell.ch);
+            }
+        }
     }
 
     fn draw_actions(&self) {
-        for (i, text) in ACTION_STRINGS[self.state as usize].iter().enumerate() {
+        for (i, text) in ACTION_STRINGS[self.game.state() as usize].iter().enumerate() {
             self.rb.print(self.actions_pos.0, self.actions_pos.1 + i,
                           rustbox::RB_NORMAL, Color::Default, Color::Default, text);
         }
     }
 
+    /// Draws a table of stats for each difficulty where the grid would be.
+    fn draw_stats(&self) {
+        let columns = [
+            ("Easy", Some(Difficulty::Easy)),
+            ("Medium", Some(Difficulty::Medium)),
+            ("Hard", Some(Difficulty::Hard)),
+            ("Daily", Some(Difficulty::Daily)),
+            ("Custom", None),
+        ];
+        let stats: Vec<Stats> = columns.iter()
+            .map(|&(_, difficulty)| Stats::new(self.game.history(), difficulty))
+            .collect();
+
+        let mut rows: Vec<(String, Vec<String>)> = vec![
+            (String::new(), columns.iter().map(|c| c.0.to_string()).collect()),
+            ("Played".to_string(), stats.iter().map(|s| s.played.to_string()).collect()),
+            ("Won".to_string(), stats.iter().map(|s| s.won.to_string()).collect()),
+            ("Win rate".to_string(), stats.iter().map(|s| format!("{:.0}%", s.win_rate() * 100.0)).collect()),
+            ("Streak".to_string(), stats.iter().map(|s| s.streak.to_string()).collect()),
+            ("Best streak".to_string(), stats.iter().map(|s| s.best_streak.to_string()).collect()),
+            ("Retries".to_string(), stats.iter().map(|s| s.retries.to_string()).collect()),
+            ("Best time".to_string(), stats.iter().map(|s| match s.best_time {
+                Some(time) => format!("{:03}", time.as_secs()),
+                None => "-".to_string(),
+            }).collect()),
+            (String::new(), Vec::new()),
+        ];
+        // How long games took, whether won or lost.
+        for i in 0..=LENGTH_BOUNDS.len() {
+            let label = match (i.checked_sub(1).map(|j| LENGTH_BOUNDS[j]), LENGTH_BOUNDS.get(i)) {
+                (None, Some(to)) => format!("Under {}", format_secs(*to)),
+                (Some(from), Some(to)) => format!("{} to {}", format_secs(from), format_secs(*to)),
+                (Some(from), None) => format!("Over {}", format_secs(from)),
+                (None, None) => unreachable!(),
+            };
+            rows.push((label, stats.iter().map(|s| s.lengths[i].to_string()).collect()));
+        }
+
+        for (i, (label, values)) in rows.iter().enumerate() {
+            let mut line = format!("{:<11}", label);
+            for value in values {
+                line.push_str(&format!("{:>8}", value));
+            }
+            self.rb.print(self.grid_pos.0, self.grid_pos.1 + i,
+                          rustbox::RB_NORMAL, Color::Default, Color::Default, &line);
+        }
+    }
+
+    /// Draws the high score table being shown where the grid would be.
+    fn draw_scores(&self) {
+        let board = self.game.scores_board();
+        self.rb.print(self.grid_pos.0, self.grid_pos.1,
+                      rustbox::RB_BOLD, Color::Default, Color::Default, &board_name(board));
+        let table = self.game.high_scores().table(board);
+        if table.is_empty() {
+            self.rb.print(self.grid_pos.0, self.grid_pos.1 + 2,
+                          rustbox::RB_NORMAL, Color::Default, Color::Default, "No wins yet");
+        }
+        for (i, score) in table.iter().enumerate() {
+            let line = format!("{:>2}. {:<w$}  {:03}", i + 1, score.name, score.time.as_secs(),
+                               w = MAX_NAME_LEN);
+            self.rb.print(self.grid_pos.0, self.grid_pos.1 + 2 + i,
+                          rustbox::RB_NORMAL, Color::Default, Color::Default, &line);
+        }
+    }
+
     fn draw_status(&self) {
-        let status = match self.state {
+        let status = match self.game.state() {
             GameState::Play => "Play!",
             GameState::Lose => "You lose...",
             GameState::Win => "You win!",
             GameState::New => "Choose a difficulty",
+            GameState::Edit => "Board editor",
+            GameState::Stats => "Statistics",
+            GameState::Name => "New high score!",
+            GameState::Scores => "High scores",
+            GameState::Replay => "Replay",
+            GameState::Paused => "Paused",
             _ => "",
         };
-        self.rb.print(self.status_pos.0, self.status_pos.1,
-                      rustbox::RB_NORMAL, Color::Default, Color::Default, &status);
+        let status_pos = self.status_pos();
+        self.rb.print(status_pos.0, status_pos.1,
+                      rustbox::RB_NORMAL, Color::Default, Color::Default, status);
+        if self.game.state() == GameState::New {
+            let on_off = |on| if on { "on" } else { "off" };
+            let settings = [
+                format!("Mines per cell: {}", self.game.max_mines()),
+                format!("Wrap edges: {}", on_off(self.game.topology() == Topology::Torus)),
+                format!("Hex tiles: {}", on_off(self.game.tiling() == Tiling::Hex)),
+                format!("Layers: {}", self.game.depth()),
+                match self.game.next_puzzle().and_then(|i| self.game.pack().get(i)) {
+                    Some(puzzle) => format!("Next puzzle: {}", puzzle.name()),
+                    None => "All puzzles solved".to_string(),
+                },
+            ];
+            for (i, setting) in settings.iter().enumerate() {
+                self.rb.print(status_pos.0, status_pos.1 + 1 + i,
+                              rustbox::RB_NORMAL, Color::Default, Color::Default, setting);
+            }
+        }
+
+        if self.game.state() == GameState::Edit {
+            let grid = self.game.editor().grid();
+            let solvable = if self.game.editor().is_solvable() {
+                "Solvable: yes"
+            } else {
+                "Solvable: no"
+            };
+            let lines = [
+                format!("{}x{}, {} mines", grid.width(), grid.height(), grid.mines()),
+                solvable.to_string(),
+                self.message.clone().unwrap_or_default(),
+            ];
+            for (i, line) in lines.iter().enumerate() {
+                self.rb.print(self.grid_pos.0, status_pos.1 + i,
+                              rustbox::RB_NORMAL, Color::Default, Color::Default, line);
+            }
+            return;
+        }
+
+        if self.game.state() == GameState::Stats || self.game.state() == GameState::Scores {
+            return;
+        }
+
+        // Timer, lined up with the left edge of the grid.
+        self.rb.print(self.grid_pos.0, status_pos.1,
+                      rustbox::RB_NORMAL, Color::Default, Color::Default,
+                      &format!("Time: {:03}", self.game.timer().elapsed().as_secs()));
+
+        let depth = self.game.grid().depth();
+        if self.game.state() == GameState::New {
+            return;
+        }
+        let mut lines = Vec::new();
+        if self.game.state() == GameState::Name {
+            lines.push(format!("{}{}", NAME_PROMPT, self.game.player_name()));
+        }
+        if let Some(endless) = self.game.endless() {
+            lines.push(format!("Score: {}", endless.score()));
+        } else if let Some(index) = self.game.puzzle() {
+            let pack = self.game.pack();
+            lines.push(format!("{} ({}/{}, {} solved)", pack.get(index).unwrap().name(),
+                               index + 1, pack.len(), self.game.solved().len()));
+        } else if depth > 1 {
+            lines.push(format!("Layer: {}/{}", self.game.layer() + 1, depth));
+        }
+        if let (GameState::Win, Some(place)) = (self.game.state(), self.game.high_score()) {
+            lines.push(format!("High score #{}", place + 1));
+        }
+        if self.game.difficulty() == Some(Difficulty::Daily) {
+            lines.push(format!("Daily streak: {}", self.game.daily_streak()));
+        }
+        if self.game.is_retry() {
+            lines.push("Retrying the same board".to_string());
+        }
+        if self.game.state() == GameState::Replay {
+            if !self.game.replay_player().is_empty() {
+                lines.push(format!("Player: {}", self.game.replay_player()));
+            }
+            let (done, total) = self.game.replay_progress();
+            lines.push(format!("Clicks: {}/{}", done, total));
+        }
+        if self.game.state() == GameState::Win && self.game.endless().is_none() {
+            let clicks = self.game.clicks();
+            lines.push(format!("3BV: {}  3BV/s: {:.2}", self.game.bbbv(), self.game.bbbv_per_second()));
+            lines.push(format!("Clicks: {} ({}+{}+{})  Efficiency: {:.0}%", clicks.total(),
+                               clicks.left, clicks.right, clicks.chord,
+                               self.game.efficiency() * 100.0));
+        }
+        if let Some(ref message) = self.message {
+            lines.push(message.clone());
+        }
+        for (i, line) in lines.iter().enumerate() {
+            self.rb.print(self.grid_pos.0, status_pos.1 + 1 + i,
+                          rustbox::RB_NORMAL, Color::Default, Color::Default, line);
+        }
+    }
+}
+
+fn board_name(board: Board) -> String {
+    match board {
+        Board::Preset(Difficulty::Easy) => "Easy".to_string(),
+        Board::Preset(Difficulty::Medium) => "Medium".to_string(),
+        Board::Preset(Difficulty::Hard) => "Hard".to_string(),
+        Board::Preset(Difficulty::Daily) => "Daily".to_string(),
+        Board::Custom(width, height, mines) => format!("{}x{}, {} mines", width, height, mines),
+    }
+}
+
+/// Turns the terminal's focus reports on or off. Terminals that don't support
+/// them ignore the request.
+fn report_focus(on: bool) {
+    let request = if on { "\x1b[?1004h" } else { "\x1b[?1004l" };
+    if let Ok(mut tty) = fs::OpenOptions::new().write(true).open("/dev/tty") {
+        let _ = tty.write_all(request.as_bytes());
+    }
+}
+
+/// The first of `layout.txt`, `layout-2.txt` and so on in `dir` that doesn't
+/// exist yet, so saving a new layout never replaces another.
+fn free_layout_path(dir: &Path) -> PathBuf {
+    (1..)
+        .map(|i| match i {
+            1 => dir.join(format!("{}.txt", LAYOUT_NAME)),
+            i => dir.join(format!("{}-{}.txt", LAYOUT_NAME, i)),
+        })
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// Formats a whole number of seconds as seconds or minutes, e.g. "30s" or
+/// "2m".
+fn format_secs(secs: u64) -> String {
+    if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
     }
 }
 
 fn main() {
+    let mut game = Game::new(Difficulty::Easy);
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut layout_path = None;
+    if args.len() == 2 && args[0] == "--edit" {
+        // Reopen a saved layout in the editor. Saving writes back to it.
+        match layout::load(&args[1]) {
+            Ok(grid) => game.edit(&grid),
+            Err(e) => {
+                eprintln!("Could not read layout {}: {}", args[1], e);
+                process::exit(1);
+            },
+        }
+        layout_path = Some(PathBuf::from(&args[1]));
+    } else if args.len() == 2 && args[0] == "--replay" {
+        // Play back a game saved in RAWVF.
+        match replay::load(&args[1]) {
+            Ok(replay) => game.start_replay(replay),
+            Err(e) => {
+                eprintln!("Could not read replay {}: {}", args[1], e);
+                process::exit(1);
+            },
+        }
+    } else if let Some(path) = args.first() {
+        // An optional shape file gives boards an irregular outline.
+        match Mask::from_file(path) {
+            Ok(shape) => {
+                game.set_shape(Some(shape));
+                game.reset(Difficulty::Easy);
+            },
+            Err(e) => {
+                eprintln!("Could not read shape {}: {}", path, e);
+                process::exit(1);
+            },
+        }
+    }
+
+    if let Some(path) = storage::data_file("puzzles.txt") {
+        match puzzle::PuzzlePack::load_or_builtin(&path) {
+            Ok(pack) => game.set_pack(pack),
+            Err(e) => eprintln!("Could not read puzzles {}: {}", path.display(), e),
+        }
+    }
+
+    let solved_path = storage::data_file("solved-puzzles");
+    if let Some(ref path) = solved_path {
+        match puzzle::load_solved(path) {
+            Ok(solved) => game.set_solved(solved),
+            Err(e) => eprintln!("Could not read solved puzzles: {}", e),
+        }
+    }
+
+    let history_path = storage::data_file("history");
+    if let Some(ref path) = history_path {
+        match history::load(path) {
+            Ok(records) => game.set_history(records),
+            Err(e) => eprintln!("Could not read the game history: {}", e),
+        }
+    }
+
+    let scores_path = storage::data_file("high-scores");
+    if let Some(ref path) = scores_path {
+        match scores::load(path) {
+            Ok(high_scores) => game.set_high_scores(high_scores),
+            Err(e) => eprintln!("Could not read high scores: {}", e),
+        }
+    }
+
     let rb = RustBox::init(Default::default()).unwrap();
+    // Losing focus pauses the game.
+    report_focus(true);
+
+    let mut ui = Ui::new(rb, game);
+    ui.layout_path = layout_path;
+
+    while ui.game.state() != GameState::Quit {
+        ui.display();
+        let solved = ui.game.solved().len();
+        let played = ui.game.history().len();
+        let high_score = ui.game.high_score();
+        ui.update();
 
-    let mut game = Game::new(rb);
+        if let Some(ref path) = scores_path {
+            if ui.game.high_score().is_some() && ui.game.high_score() != high_score {
+                let _ = scores::save(path, ui.game.high_scores());
+            }
+        }
+
+        if let Some(ref path) = history_path {
+            for record in &ui.game.history()[played..] {
+                // As with puzzles, there's nowhere to report a failure here.
+                let _ = history::append(path, record);
+            }
+        }
+
+        if let Some(ref path) = solved_path {
+            if ui.game.solved().len() != solved {
+                // Nowhere to report this while the UI is up; the puzzle just
+                // stays unsolved next time.
+                let _ = puzzle::save_solved(path, ui.game.solved());
+            }
+        }
+    }
+    report_focus(false);
+}
+
+#[cfg(test)]
+mod ui_test {
+    use super::*;
+    use minegrid::{GridState, MineGridBuilder};
+    use headless::HeadlessTerminal;
+    use std::ops::Range;
+    use rust_mines::history::Record;
+
+    //  *2110
+    //  12*10
+    //  01121
+    //  0001*
+    const MINES: &[(u32, u32)] = &[(0, 0), (2, 1), (4, 3)];
+
+    fn new_ui() -> Ui<HeadlessTerminal> {
+        let game = Game::with_grid(MineGrid::with_mines(5, 4, MINES));
+        Ui::new(HeadlessTerminal::new(80, 24), game)
+    }
+
+    fn press(ui: &mut Ui<HeadlessTerminal>, keys: &[Key]) -> String {
+        for &key in keys {
+            ui.rb.push_key(key);
+            ui.update();
+        }
+        ui.display();
+        ui.rb.snapshot()
+    }
+
+    /// One block of a snapshot ("chars", "fg", "bg" or "style") as rows.
+    fn block<'a>(snapshot: &'a str, name: &str) -> Vec<&'a str> {
+        let header = format!("{}:", name);
+        snapshot.lines()
+            .skip_while(|&line| line != header)
+            .skip(1)
+            .take_while(|&line| !["fg:", "bg:", "style:"].contains(&line) && !line.starts_with("cursor:"))
+            .collect()
+    }
+
+    /// Some rows of a snapshot block, from the grid's left edge on.
+    fn grid_rows<'a>(snapshot: &'a str, name: &str, rows: Range<usize>) -> Vec<&'a str> {
+        block(snapshot, name)[rows].iter().map(|row| row.get(20..).unwrap_or("")).collect()
+    }
+
+    #[test]
+    fn test_play_screen() {
+        let mut ui = new_ui();
+
+        let snapshot = press(&mut ui, &[
+            Key::Right, Key::Char(' '),
+            Key::Right, Key::Down, Key::Char('f'),
+            Key::Char('?'), Key::Down, Key::Char('f'), Key::Char('f'),
+        ]);
+
+        assert_eq!(GameState::Play, ui.game.state());
+        assert_eq!(r"chars:
+Minesweeper           02
+                    #######
+Space: reveal       # 2   #
+f: flag             #  F  #
+?: toggle ? marks   #  ?  #
+Arrow keys: move    #     #
+PgUp/PgDn: layer    #######
+H/J/K/L: move 5
+Home/End: row edge
+g/G: col edge
+Tab: next hidden
+u: next number
+p: pause
+
+n: new game
+q: quit
+
+
+Play!               Time: 000
+fg:
+......................rr
+...........................
+......................g....
+.......................r...
+.......................w...
+...........................
+...........................
+...............
+..................
+.............
+................
+..............
+........
+
+...........
+.......
+
+
+.............................
+bg:
+......................ww
+...........................
+.....................b.bbb.
+.....................bbbbb.
+.....................bbbbb.
+.....................bbbbb.
+...........................
+...............
+..................
+.............
+................
+..............
+........
+
+...........
+.......
+
+
+.............................
+style:
+BBBBBBBBBBB...........BB
+...........................
+...........................
+.......................B...
+.......................B...
+...........................
+...........................
+...............
+..................
+.............
+................
+..............
+........
+
+...........
+.......
+
+
+.............................
+cursor: (23, 4)
+", snapshot);
+    }
+
+    #[test]
+    fn test_lose_screen() {
+        let mut ui = new_ui();
+
+        // A wrong flag, a right flag, then the mine in the corner.
+        let snapshot = press(&mut ui, &[
+            Key::Right, Key::Char('f'), Key::Right, Key::Down, Key::Char('f'),
+            Key::Left, Key::Left, Key::Up, Key::Char(' '),
+        ]);
+
+        assert_eq!(GameState::Lose, ui.game.state());
+        assert_eq!(r"chars:
+Minesweeper           01
+                    #######
+r: retry            #*X   #
+x: export replay    #  F  #
+n: new game         #     #
+q: quit             #    *#
+                    #######
+
+
+
+
+
+
+
+
+
+
+
+You lose...         Time: 000
+fg:
+......................rr
+...........................
+.....................kr....
+.......................r...
+...........................
+.........................k.
+...........................
+
+
+
+
+
+
+
+
+
+
+
+.............................
+bg:
+......................ww
+...........................
+.....................rwbbb.
+.....................bbbbb.
+.....................bbbbb.
+.....................bbbbw.
+...........................
+
+
+
+
+
+
+
+
+
+
+
+.............................
+style:
+BBBBBBBBBBB...........BB
+...........................
+.....................BB....
+.......................B...
+...........................
+.........................B.
+...........................
+
+
+
+
+
+
+
+
+
+
+
+.............................
+cursor: (-1, -1)
+", snapshot);
+    }
+
+    #[test]
+    fn test_win_screen() {
+        let mut ui = new_ui();
+
+        let snapshot = press(&mut ui, &[
+            Key::Right, Key::Right, Key::Right, Key::Right, Key::Char(' '),
+            Key::Left, Key::Left, Key::Left, Key::Left, Key::Down, Key::Down, Key::Char(' '),
+            Key::Up, Key::Up, Key::Right, Key::Char(' '),
+            Key::Right, Key::Char(' '), Key::Esc,
+        ]);
+
+        assert_eq!(GameState::Win, ui.game.state());
+        assert_eq!(r"chars:
+Minesweeper           00
+                    #######
+x: export replay    #F211 #
+n: new game         #12F1 #
+q: quit             # 1121#
+                    #   1F#
+                    #######
+
+
+
+
+
+
+
+
+
+
+
+You win!            Time: 000
+                    3BV: 4  3BV/s: 4.00
+                    Clicks: 4 (4+0+0)  Efficiency: 100%
+fg:
+......................rr
+...........................
+.....................rgbb..
+.....................bgrb..
+......................bbgb.
+........................br.
+...........................
+
+
+
+
+
+
+
+
+
+
+
+.............................
+.......................................
+.......................................................
+bg:
+......................ww
+...........................
+.....................b.....
+.......................b...
+...........................
+.........................b.
+...........................
+
+
+
+
+
+
+
+
+
+
+
+.............................
+.......................................
+.......................................................
+style:
+BBBBBBBBBBB...........BB
+...........................
+.....................B.....
+.......................B...
+...........................
+.........................B.
+...........................
+
+
+
+
+
+
+
+
+
+
+
+.............................
+.......................................
+.......................................................
+cursor: (-1, -1)
+", snapshot);
+    }
+
+    #[test]
+    fn test_torus_screen() {
+        let game = Game::with_grid(MineGridBuilder::new(5, 4, 0)
+            .topology(Topology::Torus)
+            .mine_points(MINES)
+            .build());
+        let mut ui = Ui::new(HeadlessTerminal::new(80, 24), game);
+
+        let snapshot = press(&mut ui, &[
+            Key::Left, Key::Char(' '),
+            Key::Up, Key::Char('f'),
+        ]);
+
+        assert_eq!((4, 3), ui.game.cursor());
+        // Past each edge, a hint shows the cells on the far side.
+        assert_eq!(vec!["F    F ", "2    2 ", "       ", "       ", "F    F ", "2    2 "],
+                   grid_rows(&snapshot, "chars", 1..7));
+        assert_eq!(vec!["WUUUUWU", "U.....U", "U.....U", "U.....U", "W....BU", "UUUUUUU"],
+                   grid_rows(&snapshot, "style", 1..7));
+    }
+
+    #[test]
+    fn test_layer_screen() {
+        let game = Game::with_grid(MineGridBuilder::new(5, 4, 0)
+            .depth(2)
+            .mine_points_at(&[(0, 0, 0), (4, 3, 1)])
+            .build());
+        let mut ui = Ui::new(HeadlessTerminal::new(80, 24), game);
+
+        let snapshot = press(&mut ui, &[
+            Key::Right, Key::Right, Key::Char(' '),
+            Key::PageUp, Key::Right, Key::Right,
+            Key::Down, Key::Down, Key::Down, Key::Char('f'),
+        ]);
+
+        assert_eq!(1, ui.game.layer());
+        assert!(snapshot.contains("PgUp/PgDn: layer"));
+        // The second layer, where the reveal on the first spread to.
+        assert_eq!(vec!["# 1   #", "#11   #", "#   11#", "#   1F#"], grid_rows(&snapshot, "chars", 2..6));
+        assert!(snapshot.contains("Layer: 2/2"));
+
+        // 'g' moves within the layer and PageDown goes back down.
+        press(&mut ui, &[Key::Char('g'), Key::PageDown]);
+        assert_eq!((0, (4, 0)), (ui.game.layer(), ui.game.cursor()));
+    }
+
+    #[test]
+    fn test_shape_screen() {
+        let game = Game::with_grid(MineGridBuilder::new(0, 0, 0)
+            .mask(Mask::from_ascii("  ###\n#### #\n  ###"))
+            .mine_points(&[(4, 0)])
+            .build());
+        let mut ui = Ui::new(HeadlessTerminal::new(80, 24), game);
+
+        let snapshot = press(&mut ui, &[Key::Down, Key::Left, Key::Left, Key::Char(' '), Key::Esc]);
+
+        assert_eq!((0, 1), ui.game.cursor());
+        // The outline follows the shape, with holes left blank.
+        assert_eq!(vec!["  #####", "### 1F##", "#   1#1#", "###   ##", "  #####"],
+                   grid_rows(&snapshot, "chars", 1..6));
+    }
+
+    #[test]
+    fn test_endless_screen() {
+        let mut game = Game::with_grid(MineGrid::with_mines(5, 4, MINES));
+        game.reset_endless(2);
+        let mut ui = Ui::new(HeadlessTerminal::new(80, 24), game);
+        ui.camera = Camera::new((12, 6));
+
+        let snapshot = press(&mut ui, &[
+            Key::Char(' '), Key::Up, Key::Up, Key::Up, Key::Up, Key::Char('f'),
+        ]);
+
+        assert_eq!((0, -4), ui.game.endless_cursor());
+        assert_eq!((-6, -6), ui.camera.origin);
+        // The camera shows a window of the endless board, with no mine count.
+        assert!(block(&snapshot, "chars")[0].ends_with("Minesweeper"));
+        assert_eq!(vec!["#      F     #", "#            #", "#            #", "#     111    #"],
+                   grid_rows(&snapshot, "chars", 4..8));
+        assert!(snapshot.contains("Score: 9"));
+    }
+
+    #[test]
+    fn test_puzzle_screen() {
+        let mut ui = new_ui();
+        ui.game.set_pack(puzzle::PuzzlePack::parse("Warm up\no1*.\n01..\n").unwrap());
+
+        let snapshot = press(&mut ui, &[Key::Char('n')]);
+        assert!(snapshot.contains("Next puzzle: Warm up"));
+
+        let snapshot = press(&mut ui, &[Key::Char('p'), Key::Down, Key::Right, Key::Right, Key::Char('f')]);
+
+        assert_eq!(Some(0), ui.game.puzzle());
+        // The puzzle starts with its opened cells revealed.
+        assert_eq!(vec!["# 1  #", "# 1F #"], grid_rows(&snapshot, "chars", 2..4));
+        assert!(snapshot.contains("Warm up (1/1, 0 solved)"));
+    }
+
+    #[test]
+    fn test_hex_screen() {
+        let game = Game::with_grid(MineGridBuilder::new(5, 4, 0)
+            .tiling(Tiling::Hex)
+            .mine_points(MINES)
+            .build());
+        let mut ui = Ui::new(HeadlessTerminal::new(80, 24), game);
+
+        let snapshot = press(&mut ui, &[
+            Key::Right, Key::Right, Key::Right, Key::Right, Key::Char(' '),
+            Key::Down, Key::Down, Key::Down, Key::Char('f'),
+        ]);
+
+        // Odd rows are shifted half a cell to the right.
+        assert_eq!(vec!["############", "#      1   #", "#       1  #", "#        1 #", "#         F#", "############"],
+                   grid_rows(&snapshot, "chars", 1..7));
+        assert_eq!(vec![".b.b.b......", "..b.b.b.....", ".b.b.b.b....", "..b.b.b.b.b."],
+                   grid_rows(&snapshot, "bg", 2..6));
+        assert!(snapshot.ends_with("cursor: (30, 5)\n"));
+    }
+
+    #[test]
+    fn test_new_screen() {
+        let mut ui = new_ui();
+
+        let snapshot = press(&mut ui, &[Key::Char('n'), Key::Char('x')]);
+
+        assert_eq!(GameState::New, ui.game.state());
+        assert_eq!(r"chars:
+Minesweeper           03
+                    #######
+e: easy             #     #
+m: medium           #     #
+h: hard             #     #
+y: daily challenge  #     #
+i: endless          #######
+p: puzzles
+b: board editor
+s: statistics
+r: high scores
+x: mines per cell
+w: wrap edges
+t: hex tiles
+d: layers
+c: cancel
+q: quit
+
+Choose a difficulty Time: 000
+Mines per cell: 2
+Wrap edges: off
+Hex tiles: off
+Layers: 1
+Next puzzle: First steps
+fg:
+......................rr
+...........................
+...........................
+...........................
+...........................
+...........................
+...........................
+..........
+...............
+.............
+..............
+.................
+.............
+............
+.........
+.........
+.......
+
+.............................
+.................
+...............
+..............
+.........
+........................
+bg:
+......................ww
+...........................
+.....................bbbbb.
+.....................bbbbb.
+.....................bbbbb.
+.....................bbbbb.
+...........................
+..........
+...............
+.............
+..............
+.................
+.............
+............
+.........
+.........
+.......
+
+.............................
+.................
+...............
+..............
+.........
+........................
+style:
+BBBBBBBBBBB...........BB
+...........................
+...........................
+...........................
+...........................
+...........................
+...........................
+..........
+...............
+.............
+..............
+.................
+.............
+............
+.........
+.........
+.......
+
+.............................
+.................
+...............
+..............
+.........
+........................
+cursor: (-1, -1)
+", snapshot);
+
+        press(&mut ui, &[Key::Char('c')]);
+        assert_eq!(GameState::Play, ui.game.state());
+    }
+
+    #[test]
+    fn test_stats_screen() {
+        let mut ui = new_ui();
+        ui.game.set_history(vec![Record {
+            difficulty: Some(Difficulty::Easy),
+            width: 9,
+            height: 9,
+            mines: 10,
+            seed: Some(1),
+            won: true,
+            time: Duration::from_secs(75),
+            clicks: 30,
+            bbbv: 20,
+            retry: false,
+        }]);
+
+        let snapshot = press(&mut ui, &[Key::Char(' '), Key::Char('n'), Key::Char('s')]);
+
+        assert_eq!(GameState::Stats, ui.game.state());
+        assert_eq!(vec![
+            "               Easy  Medium    Hard   Daily  Custom",
+            "Played            1       0       0       0       1",
+            "Won               1       0       0       0       0",
+            "Win rate       100%      0%      0%      0%      0%",
+            "Streak            1       0       0       0       0",
+            "Best streak       1       0       0       0       0",
+            "Retries           0       0       0       0       0",
+            "Best time       075       -       -       -       -",
+            "",
+            "Under 30s         0       0       0       0       1",
+            "30s to 1m         0       0       0       0       0",
+            "1m to 2m          1       0       0       0       0",
+            "2m to 5m          0       0       0       0       0",
+            "Over 5m           0       0       0       0       0",
+        ], grid_rows(&snapshot, "chars", 1..15));
+
+        press(&mut ui, &[Key::Char('c')]);
+        assert_eq!(GameState::New, ui.game.state());
+    }
+
+    #[test]
+    fn test_high_score_screens() {
+        let mut ui = new_ui();
+
+        let snapshot = press(&mut ui, &[
+            Key::Right, Key::Right, Key::Right, Key::Right, Key::Char(' '),
+            Key::Left, Key::Left, Key::Left, Key::Left, Key::Down, Key::Down, Key::Char(' '),
+            Key::Up, Key::Up, Key::Right, Key::Char(' '),
+            Key::Right, Key::Char(' '), Key::Char('A'), Key::Char('n'), Key::Char('q'),
+            Key::Backspace, Key::Char('n'),
+        ]);
+
+        assert_eq!(GameState::Name, ui.game.state());
+        // The name is typed on the line under the status, after the prompt.
+        assert!(snapshot.contains("New high score!     Time: 000\n                    Name: Ann\n"));
+        assert!(snapshot.ends_with("cursor: (29, 19)\n"));
+
+        let snapshot = press(&mut ui, &[Key::Enter, Key::Char('n'), Key::Char('r')]);
+
+        assert_eq!(GameState::Scores, ui.game.state());
+        assert_eq!(vec!["5x4, 3 mines", "", " 1. Ann               000"], grid_rows(&snapshot, "chars", 1..4));
+        assert_eq!("....................BBBBBBBBBBBB", block(&snapshot, "style")[1]);
+
+        press(&mut ui, &[Key::Right, Key::Char('c')]);
+        assert_eq!(GameState::New, ui.game.state());
+    }
+
+    #[test]
+    fn test_replay_screen() {
+        let mut ui = new_ui();
+        ui.game.start_replay(replay::parse("Player: Ann
+Width: 5
+Height: 4
+Mines: 3
+Board:
+*0000
+00*00
+00000
+0000*
+Events:
+0.00 lr 1 3 (8 40)
+0.00 rr 1 1 (8 8)
+60.00 lr 2 1 (24 8)
+").unwrap());
+
+        let snapshot = press(&mut ui, &[Key::Char('z')]);
+
+        assert_eq!(GameState::Replay, ui.game.state());
+        assert_eq!((2, 3), ui.game.replay_progress());
+        // The clicks due so far are played, and the cursor is on the last.
+        assert_eq!(vec!["#F    #", "#12   #", "# 112 #", "#   1 #"], grid_rows(&snapshot, "chars", 2..6));
+        assert!(snapshot.contains("Replay              Time: 000\n                    Player: Ann\n                    Clicks: 2/3\n"));
+        assert!(snapshot.ends_with("cursor: (21, 2)\n"));
+
+        press(&mut ui, &[Key::Char('n')]);
+        assert_eq!(GameState::New, ui.game.state());
+    }
+
+    #[test]
+    fn test_pause_screen() {
+        let mut ui = new_ui();
+
+        let snapshot = press(&mut ui, &[Key::Right, Key::Char(' '), Key::Char('p')]);
+
+        assert_eq!(GameState::Paused, ui.game.state());
+        // Only the border is drawn, so the board can't be studied.
+        assert_eq!(vec!["#######", "#     #", "#     #", "#     #", "#     #", "#######"],
+                   grid_rows(&snapshot, "chars", 1..7));
+        assert!(grid_rows(&snapshot, "bg", 1..7).iter().all(|row| !row.contains('b')));
+        assert!(snapshot.contains("Paused              Time: 000"));
+
+        press(&mut ui, &[Key::Left]);
+        assert_eq!(GameState::Play, ui.game.state());
+        assert_eq!((1, 0), ui.game.cursor());
+
+        ui.rb.push_event(Event::ResizeEvent(100, 30));
+        ui.update();
+        assert_eq!(GameState::Paused, ui.game.state());
+    }
+
+    #[test]
+    fn test_focus_report() {
+        let mut ui = new_ui();
+        press(&mut ui, &[Key::Right, Key::Char(' ')]);
+
+        let queue = |ui: &mut Ui<HeadlessTerminal>, keys: &[Key]| {
+            for &key in keys {
+                ui.rb.push_key(key);
+            }
+        };
+
+        // Gaining focus changes nothing, and losing it pauses.
+        queue(&mut ui, &[Key::Esc, Key::Char('['), Key::Char('I')]);
+        ui.update();
+        assert_eq!(GameState::Play, ui.game.state());
+        assert_eq!((1, 0), ui.game.cursor());
+        queue(&mut ui, &[Key::Esc, Key::Char('['), Key::Char('O')]);
+        ui.update();
+        assert_eq!(GameState::Paused, ui.game.state());
+
+        // Keys typed after a real Esc aren't lost.
+        press(&mut ui, &[Key::Char('x')]);
+        queue(&mut ui, &[Key::Esc, Key::Char('['), Key::Right]);
+        ui.update();
+        assert!(ui.rb.poll_event(false).is_ok_and(|e| matches!(e, Event::NoEvent)));
+        ui.update();
+        ui.update();
+        assert_eq!((2, 0), ui.game.cursor());
+    }
+
+    #[test]
+    fn test_retry_screen() {
+        let mut ui = new_ui();
+
+        let snapshot = press(&mut ui, &[Key::Right, Key::Char(' '), Key::Left, Key::Char(' ')]);
+        assert!(snapshot.contains("r: retry"));
+
+        let snapshot = press(&mut ui, &[Key::Char('r')]);
+
+        assert_eq!(GameState::Play, ui.game.state());
+        assert_eq!(vec!["#     #"; 4], grid_rows(&snapshot, "chars", 2..6));
+        assert!(snapshot.contains("Play!               Time: 000\n                    Retrying the same board\n"));
+    }
+
+    #[test]
+    fn test_daily_screen() {
+        let mut ui = new_ui();
+        ui.game.start_daily(1);
+
+        let snapshot = press(&mut ui, &[]);
+
+        assert_eq!(Some(Difficulty::Daily), ui.game.difficulty());
+        assert!(block(&snapshot, "chars")[0].ends_with(" 40"));
+        assert_eq!(18, grid_rows(&snapshot, "chars", 1..2)[0].len());
+        assert!(snapshot.contains("Play!               Time: 000\n                    Daily streak: 0\n"));
+
+        let snapshot = press(&mut ui, &[Key::Char('n')]);
+        assert!(snapshot.contains("y: daily challenge"));
+    }
+
+    #[test]
+    fn test_navigation_keys() {
+        let mut ui = new_ui();
+
+        press(&mut ui, &[Key::End, Key::Char('G')]);
+        assert_eq!((4, 3), ui.game.cursor());
+        press(&mut ui, &[Key::Char('H')]);
+        assert_eq!((0, 3), ui.game.cursor());
+        press(&mut ui, &[Key::Char(' '), Key::Home, Key::Char('g'), Key::Tab]);
+        assert_eq!((1, 0), ui.game.cursor());
+        press(&mut ui, &[Key::Char('u')]);
+        assert_eq!((0, 1), ui.game.cursor());
+    }
+
+    #[test]
+    fn test_editor_screen() {
+        let mut ui = new_ui();
+
+        let snapshot = press(&mut ui, &[
+            Key::Char('n'), Key::Char('b'), Key::Char(' '), Key::Right, Key::Right,
+            Key::Char('o'), Key::Char('-'), Key::Char(']'),
+        ]);
+
+        assert_eq!(GameState::Edit, ui.game.state());
+        // The mine and the opened cell show, and the rest stays hidden.
+        assert_eq!(vec!["#*1      #", "#11      #"], grid_rows(&snapshot, "chars", 2..4));
+        assert_eq!(vec![".rb.bbbbb.", ".bbbbbbbb."], grid_rows(&snapshot, "bg", 2..4));
+        assert!(snapshot.contains("Board editor        8x10, 1 mines"));
+        assert!(snapshot.contains("Solvable: yes"));
+
+        press(&mut ui, &[Key::Char('p')]);
+        assert_eq!(GameState::Play, ui.game.state());
+        assert_eq!(1, ui.game.grid().mines());
+    }
+
+    #[test]
+    fn test_free_layout_path() {
+        let dir = env::temp_dir().join(format!("rust-mines-layouts-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(dir.join("layout.txt"), free_layout_path(&dir));
+
+        fs::write(dir.join("layout.txt"), "*\n").unwrap();
+        fs::write(dir.join("layout-3.txt"), "*\n").unwrap();
+        let path = free_layout_path(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(dir.join("layout-2.txt"), path);
+    }
+
+    #[test]
+    fn test_cell_renderer_numbers() {
+        //  *****4*31
+        //  *8*7*6**1
+        //  ****5**31
+        //  234**3210
+        let mines = [
+            (0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (6, 0),
+            (0, 1), (2, 1), (4, 1), (6, 1), (7, 1),
+            (0, 2), (1, 2), (2, 2), (3, 2), (5, 2), (6, 2),
+            (3, 3), (4, 3),
+        ];
+        let mut grid = MineGrid::with_mines(9, 4, &mines);
+        for j in 0..grid.height() {
+            for i in 0..grid.width() {
+                if !mines.contains(&(i, j)) {
+                    grid.reveal(i, j);
+                }
+            }
+        }
+        assert_eq!(GridState::Win, grid.state());
+
+        let mut rendered = String::new();
+        for (x, _, cell) in CellRenderer::new(&grid, 0) {
+            rendered.push(cell.ch);
+            rendered.push(headless::color_char(cell.fg));
+            rendered.push(headless::color_char(cell.bg));
+            rendered.push(if x + 1 == grid.width() { '\n' } else { ' ' });
+        }
+        assert_eq!(r"Frb Frb Frb Frb Frb 4y. Frb 3r. 1b.
+Frb 8wm Frb 7wc Frb 6c. Frb Frb 1b.
+Frb Frb Frb Frb 5m. Frb Frb 3r. 1b.
+2g. 3r. 4y. Frb Frb 3r. 2g. 1b.  ..
+", rendered);
+    }
+
+    #[test]
+    fn test_cell_renderer_multimines() {
+        //  1333.
+        //  3+96.
+        let mut mines = vec![(0, 0)];
+        for &point in &[(1, 0), (2, 0), (3, 0), (0, 1)] {
+            mines.extend_from_slice(&[point; 3]);
+        }
+        let mut grid = MineGrid::with_mines(5, 2, &mines);
+        grid.toggle_flag(0, 0);
+        for _ in 0..2 {
+            grid.toggle_flag(1, 0);
+        }
+        for _ in 0..3 {
+            grid.toggle_flag(2, 0);
+        }
+        // Leave the last column hidden, as winning would flag every mine.
+        grid.reveal(1, 1);
+        grid.reveal(2, 1);
+        grid.reveal(3, 1);
 
-    while game.state != GameState::Quit {
-        game.display();
-        game.update();
+        let rendered: String = CellRenderer::new(&grid, 0)
+            .map(|(_, _, cell)| cell.ch)
+            .collect();
+        assert_eq!("F23   +96 ", rendered);
     }
 }
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use minegrid::MineGrid;
+use minegrid::layout::{self, LayoutError};
+
+/// The puzzles that ship with the game, played when there is no pack file.
+const BUILTIN_PACK: &str = include_str!("../puzzles.txt");
+
+/// A partially revealed board that can be finished without guessing.
+pub struct Puzzle {
+    name: String,
+    layout: String,
+}
+
+/// A list of puzzles read from text. Puzzles are separated by blank lines;
+/// the first line of each is its name and the rest is its layout, in the
+/// format of `minegrid::layout`.
+pub struct PuzzlePack {
+    puzzles: Vec<Puzzle>,
+}
+
+impl Puzzle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// A fresh board for this puzzle.
+    pub fn grid(&self) -> MineGrid {
+        // Layouts are checked when the pack is parsed.
+        layout::parse(&self.layout).unwrap()
+    }
+}
+
+impl PuzzlePack {
+    pub fn parse(text: &str) -> Result<PuzzlePack, LayoutError> {
+        let mut puzzles = Vec::new();
+        let mut lines = text.lines().peekable();
+        loop {
+            while lines.peek() == Some(&"") {
+                lines.next();
+            }
+            let name = match lines.next() {
+                Some(name) => name.to_string(),
+                None => break,
+            };
+
+            let mut layout = String::new();
+            while let Some(line) = lines.next_if(|line| !line.is_empty()) {
+                layout.push_str(line);
+                layout.push('\n');
+            }
+            layout::parse(&layout)?;
+            puzzles.push(Puzzle { name, layout });
+        }
+        Ok(PuzzlePack { puzzles })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PuzzlePack, LayoutError> {
+        PuzzlePack::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Reads the pack at `path`, or the built-in one if there is no file
+    /// there.
+    pub fn load_or_builtin<P: AsRef<Path>>(path: P) -> Result<PuzzlePack, LayoutError> {
+        match PuzzlePack::load(path) {
+            Err(LayoutError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => Ok(PuzzlePack::builtin()),
+            result => result,
+        }
+    }
+
+    pub fn builtin() -> PuzzlePack {
+        PuzzlePack::parse(BUILTIN_PACK).unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.puzzles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.puzzles.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Puzzle> {
+        self.puzzles.get(index)
+    }
+}
+
+/// Reads the names of solved puzzles, one per line. A missing file means none
+/// are solved yet.
+pub fn load_solved<P: AsRef<Path>>(path: P) -> io::Result<HashSet<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text.lines().filter(|line| !line.is_empty()).map(String::from).collect()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn save_solved<P: AsRef<Path>>(path: P, solved: &HashSet<String>) -> io::Result<()> {
+    let mut names: Vec<&String> = solved.iter().collect();
+    names.sort();
+    let mut text = String::new();
+    for name in names {
+        text.push_str(name);
+        text.push('\n');
+    }
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod puzzle_test {
+    use super::*;
+    use minegrid::solver::is_solvable;
+    use minegrid::GridState;
+
+    #[test]
+    fn test_parse() {
+        let pack = PuzzlePack::parse("\nOne\no1*.\n\n\nTwo\n*1\n11\n").unwrap();
+
+        assert_eq!(2, pack.len());
+        assert_eq!("One", pack.get(0).unwrap().name());
+        assert_eq!(4, pack.get(0).unwrap().grid().width());
+        assert_eq!(2, pack.get(1).unwrap().grid().height());
+        assert!(pack.get(2).is_none());
+
+        assert!(PuzzlePack::parse("Bad\n*2.").is_err());
+    }
+
+    #[test]
+    fn test_load_or_builtin() {
+        let path = std::env::temp_dir().join(format!("rust-mines-pack-{}", std::process::id()));
+        let missing = PuzzlePack::load_or_builtin(&path).unwrap();
+        assert_eq!(PuzzlePack::builtin().len(), missing.len());
+
+        fs::write(&path, "Only\n*1\n").unwrap();
+        let pack = PuzzlePack::load_or_builtin(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!("Only", pack.unwrap().get(0).unwrap().name());
+    }
+
+    #[test]
+    fn test_builtin_pack() {
+        let pack = PuzzlePack::builtin();
+        assert!(!pack.is_empty());
+
+        let mut names = HashSet::new();
+        for i in 0..pack.len() {
+            let puzzle = pack.get(i).unwrap();
+            let grid = puzzle.grid();
+            assert!(names.insert(puzzle.name()), "{} is in the pack twice", puzzle.name());
+            assert_eq!(GridState::Play, grid.state(), "{} is already solved", puzzle.name());
+            assert!(is_solvable(&grid), "{} needs a guess", puzzle.name());
+        }
+    }
+}
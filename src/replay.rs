@@ -0,0 +1,300 @@
+//! Games as RAWVF text, the replay format shared by other Minesweeper
+//! clients: a header of `Key: value` lines, the board under `Board:` with `*`
+//! for mines and `0` for safe cells, then timed mouse events under `Events:`.
+//! Cells are counted from 1 and pixel positions assume 16 pixel cells.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use minegrid::{GridState, MineGrid, Tiling, Topology};
+
+const VERSION: &str = "Rev3";
+const CELL_PIXELS: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClickKind {
+    Left,
+    Right,
+    /// Reveals the neighbors of a number, given as a middle click.
+    Chord,
+}
+
+/// A click on the cell at (x, y), `time` after the game started.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Click {
+    pub time: Duration,
+    pub kind: ClickKind,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A game read from a RAWVF file.
+pub struct Replay {
+    pub player: String,
+    /// The board before the first click.
+    pub grid: MineGrid,
+    pub clicks: Vec<Click>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// A header line the file needs but doesn't have.
+    Missing(&'static str),
+    /// The board doesn't match the width and height.
+    BadBoard,
+    /// An event that can't be read, on the given line counting from 1.
+    BadEvent(usize),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Io(ref e) => write!(f, "{}", e),
+            ReplayError::Missing(key) => write!(f, "missing {}", key),
+            ReplayError::BadBoard => write!(f, "board doesn't match its size"),
+            ReplayError::BadEvent(line) => write!(f, "bad event on line {}", line),
+        }
+    }
+}
+
+impl error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> ReplayError {
+        ReplayError::Io(e)
+    }
+}
+
+impl ClickKind {
+    /// Names of the button's press and release events.
+    fn events(&self) -> (&'static str, &'static str) {
+        match *self {
+            ClickKind::Left => ("lc", "lr"),
+            ClickKind::Right => ("rc", "rr"),
+            ClickKind::Chord => ("mc", "mr"),
+        }
+    }
+}
+
+/// Writes a game played on `grid` as RAWVF. Returns `None` for boards the
+/// format can't hold: anything but a plain rectangle with one mine per cell.
+pub fn write(grid: &MineGrid, clicks: &[Click], player: &str, time: Duration) -> Option<String> {
+    if grid.depth() != 1 || !grid.mask().is_full() || grid.max_mines() != 1 ||
+        grid.tiling() != Tiling::Square || grid.topology() != Topology::Bounded {
+        return None;
+    }
+
+    let level = match (grid.width(), grid.height(), grid.mines()) {
+        (9, 9, 10) => "Beginner",
+        (16, 16, 40) => "Intermediate",
+        (30, 16, 99) => "Expert",
+        _ => "Custom",
+    };
+    let mut text = String::new();
+    for &(key, ref value) in &[
+        ("RawVF_Version", VERSION.to_string()),
+        ("Program", "rust-mines".to_string()),
+        ("Version", env!("CARGO_PKG_VERSION").to_string()),
+        ("Player", player.to_string()),
+        ("Level", level.to_string()),
+        ("Width", grid.width().to_string()),
+        ("Height", grid.height().to_string()),
+        ("Mines", grid.mines().to_string()),
+        ("Marks", if grid.question_marks() { "On" } else { "Off" }.to_string()),
+        ("Time", format_time(time)),
+        ("BBBV", grid.bbbv().to_string()),
+    ] {
+        text.push_str(&format!("{}: {}\n", key, value));
+    }
+
+    text.push_str("Board:\n");
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            text.push(if grid.get_cell(x, y).unwrap().mines() != 0 { '*' } else { '0' });
+        }
+        text.push('\n');
+    }
+
+    text.push_str("Events:\n");
+    for click in clicks {
+        let (press, release) = click.kind.events();
+        let (px, py) = (click.x * CELL_PIXELS + CELL_PIXELS / 2, click.y * CELL_PIXELS + CELL_PIXELS / 2);
+        for event in &[press, release] {
+            text.push_str(&format!("{} {} {} {} ({} {})\n",
+                                   format_time(click.time), event, click.x + 1, click.y + 1, px, py));
+        }
+    }
+    let end = match grid.state() {
+        GridState::Win => Some("won"),
+        GridState::Lose => Some("blast"),
+        GridState::Play => None,
+    };
+    if let Some(end) = end {
+        text.push_str(&format!("{} {}\n", format_time(time), end));
+    }
+    Some(text)
+}
+
+/// Reads a RAWVF game. Clicks happen when the button is released, and
+/// events other than releases are skipped, as are clicks off the board.
+pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+    let mut lines = text.lines().enumerate();
+    let mut header = Vec::new();
+    for (_, line) in lines.by_ref() {
+        if line.trim() == "Board:" {
+            break;
+        }
+        if let Some(i) = line.find(':') {
+            header.push((line[..i].trim(), line[i + 1..].trim()));
+        }
+    }
+    let field = |key: &'static str| {
+        header.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v).ok_or(ReplayError::Missing(key))
+    };
+    let width: u32 = field("Width")?.parse().map_err(|_| ReplayError::Missing("Width"))?;
+    let height: u32 = field("Height")?.parse().map_err(|_| ReplayError::Missing("Height"))?;
+    let player = field("Player").unwrap_or("").to_string();
+
+    let mut mine_points = Vec::new();
+    for y in 0..height {
+        let row: Vec<char> = match lines.next() {
+            Some((_, line)) => line.trim().chars().collect(),
+            None => return Err(ReplayError::BadBoard),
+        };
+        if row.len() != width as usize {
+            return Err(ReplayError::BadBoard);
+        }
+        for (x, &ch) in row.iter().enumerate() {
+            if ch == '*' {
+                mine_points.push((x as u32, y));
+            }
+        }
+    }
+
+    match lines.next() {
+        Some((_, line)) if line.trim() == "Events:" => {},
+        _ => return Err(ReplayError::Missing("Events")),
+    }
+    let mut clicks = Vec::new();
+    for (i, line) in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let kind = match fields.get(1) {
+            Some(&"lr") => ClickKind::Left,
+            Some(&"rr") => ClickKind::Right,
+            Some(&"mr") => ClickKind::Chord,
+            _ => continue,
+        };
+        let (time, x, y) = parse_event(&fields).ok_or(ReplayError::BadEvent(i + 1))?;
+        // Other clients log clicks off the board with a 0 for the cell.
+        if x == 0 || y == 0 || x > width || y > height {
+            continue;
+        }
+        clicks.push(Click { time, kind, x: x - 1, y: y - 1 });
+    }
+
+    Ok(Replay {
+        player,
+        grid: MineGrid::with_mines(width, height, &mine_points),
+        clicks,
+    })
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Reads the time and cell, counting from 1, of an event:
+/// "<seconds> <event> <x> <y> ...".
+fn parse_event(fields: &[&str]) -> Option<(Duration, u32, u32)> {
+    let secs: f64 = fields.first()?.parse().ok()?;
+    let x: u32 = fields.get(2)?.parse().ok()?;
+    let y: u32 = fields.get(3)?.parse().ok()?;
+    if !(secs >= 0.0 && secs.is_finite()) {
+        return None;
+    }
+    Some((Duration::from_secs_f64(secs), x, y))
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.3}", time.as_secs_f64())
+}
+
+#[cfg(test)]
+mod replay_test {
+    use super::*;
+    use minegrid::MineGridBuilder;
+
+    fn click(millis: u64, kind: ClickKind, x: u32, y: u32) -> Click {
+        Click { time: Duration::from_millis(millis), kind, x, y }
+    }
+
+    #[test]
+    fn test_write() {
+        let mut grid = MineGrid::with_mines(3, 2, &[(0, 0)]);
+        let clicks = [
+            click(0, ClickKind::Left, 2, 1),
+            click(500, ClickKind::Left, 0, 1),
+            click(1500, ClickKind::Right, 0, 0),
+        ];
+        grid.reveal(2, 1);
+        grid.reveal(0, 1);
+
+        assert_eq!("RawVF_Version: Rev3
+Program: rust-mines
+Version: 0.0.1
+Player: Ann
+Level: Custom
+Width: 3
+Height: 2
+Mines: 1
+Marks: Off
+Time: 1.500
+BBBV: 2
+Board:
+*00
+000
+Events:
+0.000 lc 3 2 (40 24)
+0.000 lr 3 2 (40 24)
+0.500 lc 1 2 (8 24)
+0.500 lr 1 2 (8 24)
+1.500 rc 1 1 (8 8)
+1.500 rr 1 1 (8 8)
+1.500 won
+", write(&grid, &clicks, "Ann", Duration::from_millis(1500)).unwrap());
+
+        let hex = MineGridBuilder::new(3, 2, 1).tiling(Tiling::Hex).build();
+        assert!(write(&hex, &[], "Ann", Duration::from_secs(0)).is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        let grid = MineGrid::with_mines(4, 3, &[(1, 0), (3, 2)]);
+        let clicks = [
+            click(0, ClickKind::Left, 0, 2),
+            click(250, ClickKind::Right, 1, 0),
+            click(1125, ClickKind::Chord, 0, 1),
+        ];
+        let text = write(&grid, &clicks, "Bo", Duration::from_secs(2)).unwrap();
+
+        let replay = parse(&text).unwrap();
+        assert_eq!("Bo", replay.player);
+        assert_eq!(4, replay.grid.width());
+        assert_eq!(2, replay.grid.mines());
+        assert_eq!(1, replay.grid.get_cell(1, 0).unwrap().mines());
+        assert_eq!(&clicks[..], &replay.clicks[..]);
+
+        // Moves, presses and clicks off the board are skipped.
+        let replay = parse("Width: 2\nHeight: 1\nBoard:\n*0\nEvents:\n0.00 start\n0.10 mv 1 1 (8 8)\n0.20 lc 2 1 (24 8)\n0.25 lr 2 1 (24 8)\n0.30 lr 0 1 (-4 8)\n0.40 rr 3 1 (40 8)\n").unwrap();
+        assert_eq!(vec![click(250, ClickKind::Left, 1, 0)], replay.clicks);
+
+        assert!(matches!(parse("Width: 2\nBoard:\n"), Err(ReplayError::Missing("Height"))));
+        assert!(matches!(parse("Width: 2\nHeight: 1\nBoard:\n*\nEvents:\n"), Err(ReplayError::BadBoard)));
+        assert!(matches!(parse("Width: 1\nHeight: 1\nBoard:\n*\nEvents:\nsoon lr 1 1\n"), Err(ReplayError::BadEvent(6))));
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use game::Difficulty;
+
+/// How many times each table keeps.
+pub const TABLE_SIZE: usize = 10;
+
+/// Which table a game goes in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Board {
+    Preset(Difficulty),
+    /// Width, height and mines of any other board.
+    Custom(u32, u32, u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    pub name: String,
+    pub time: Duration,
+}
+
+/// The fastest wins on each board, fastest first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HighScores {
+    tables: HashMap<Board, Vec<Score>>,
+}
+
+impl Board {
+    /// A short name without spaces, as kept in the file.
+    pub fn key(&self) -> String {
+        match *self {
+            Board::Preset(difficulty) => difficulty.name().to_string(),
+            Board::Custom(width, height, mines) => format!("{}x{}x{}", width, height, mines),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Board> {
+        if let Some(difficulty) = Difficulty::from_name(key) {
+            return Some(Board::Preset(difficulty));
+        }
+        let sizes: Vec<u32> = key.split('x').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+        match sizes[..] {
+            [width, height, mines] => Some(Board::Custom(width, height, mines)),
+            _ => None,
+        }
+    }
+}
+
+impl HighScores {
+    pub fn new() -> HighScores {
+        HighScores::default()
+    }
+
+    /// Reads scores from lines of board key, time in milliseconds and name,
+    /// separated by tabs. Lines that can't be read are skipped.
+    pub fn parse(text: &str) -> HighScores {
+        let mut scores = HighScores::new();
+        for line in text.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let board = fields.next().and_then(Board::from_key);
+            let time = fields.next().and_then(|time| time.parse().ok());
+            if let (Some(board), Some(time), Some(name)) = (board, time, fields.next()) {
+                scores.insert(board, Score { name: name.to_string(), time: Duration::from_millis(time) });
+            }
+        }
+        scores
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for board in self.boards() {
+            for score in self.table(board) {
+                text.push_str(&format!("{}\t{}\t{}\n", board.key(), score.time.as_millis(), score.name));
+            }
+        }
+        text
+    }
+
+    /// Every board with a table: the presets, then custom boards in order of
+    /// size.
+    pub fn boards(&self) -> Vec<Board> {
+        let mut boards: Vec<Board> = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard].iter()
+            .map(|&d| Board::Preset(d))
+            .collect();
+        let mut custom: Vec<Board> = self.tables.keys()
+            .filter(|board| !boards.contains(board))
+            .cloned()
+            .collect();
+        custom.sort();
+        boards.extend(custom);
+        boards
+    }
+
+    pub fn table(&self, board: Board) -> &[Score] {
+        self.tables.get(&board).map_or(&[], |table| &table[..])
+    }
+
+    /// Whether a win in `time` would make the table.
+    pub fn qualifies(&self, board: Board, time: Duration) -> bool {
+        let table = self.table(board);
+        table.len() < TABLE_SIZE || table.iter().any(|score| time < score.time)
+    }
+
+    /// Adds a score, dropping the slowest if the table is full. Returns its
+    /// place in the table, or `None` if it was too slow.
+    pub fn insert(&mut self, board: Board, score: Score) -> Option<usize> {
+        let table = self.tables.entry(board).or_default();
+        // Ties go after the scores already there.
+        let place = table.iter().position(|s| score.time < s.time).unwrap_or(table.len());
+        if place >= TABLE_SIZE {
+            return None;
+        }
+        table.insert(place, score);
+        table.truncate(TABLE_SIZE);
+        Some(place)
+    }
+}
+
+/// Reads the high scores. A missing file means there are none yet.
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<HighScores> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(HighScores::parse(&text)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(HighScores::new()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn save<P: AsRef<Path>>(path: P, scores: &HighScores) -> io::Result<()> {
+    fs::write(path, scores.to_text())
+}
+
+#[cfg(test)]
+mod scores_test {
+    use super::*;
+
+    fn score(name: &str, secs: u64) -> Score {
+        Score {
+            name: name.to_string(),
+            time: Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn test_insert() {
+        let easy = Board::Preset(Difficulty::Easy);
+        let mut scores = HighScores::new();
+
+        for i in 0..TABLE_SIZE as u64 {
+            assert_eq!(Some(i as usize), scores.insert(easy, score("a", 10 + i)));
+        }
+        assert!(!scores.qualifies(easy, Duration::from_secs(19)));
+        assert!(scores.qualifies(easy, Duration::from_secs(15)));
+        assert_eq!(None, scores.insert(easy, score("b", 30)));
+
+        assert_eq!(Some(1), scores.insert(easy, score("c", 10)));
+        assert_eq!(TABLE_SIZE, scores.table(easy).len());
+        assert_eq!("c", scores.table(easy)[1].name);
+        assert_eq!(Duration::from_secs(18), scores.table(easy)[TABLE_SIZE - 1].time);
+
+        assert!(scores.qualifies(Board::Preset(Difficulty::Hard), Duration::from_secs(999)));
+    }
+
+    #[test]
+    fn test_text() {
+        let mut scores = HighScores::new();
+        scores.insert(Board::Custom(30, 20, 100), score("Big board", 200));
+        scores.insert(Board::Preset(Difficulty::Medium), score("Ann", 60));
+        scores.insert(Board::Custom(5, 4, 3), score("Bo", 2));
+
+        assert_eq!("medium\t60000\tAnn\n5x4x3\t2000\tBo\n30x20x100\t200000\tBig board\n", scores.to_text());
+        assert_eq!(scores, HighScores::parse(&scores.to_text()));
+        assert_eq!(HighScores::new(), HighScores::parse("easy\tslow\tAnn\n7x7\t10\tBo\n"));
+
+        let boards = scores.boards();
+        assert_eq!(5, boards.len());
+        assert_eq!(Board::Custom(5, 4, 3), boards[3]);
+    }
+}
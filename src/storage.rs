@@ -0,0 +1,16 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Path of a file kept between runs, under `$XDG_DATA_HOME/rust-mines` or
+/// `~/.local/share/rust-mines`. Creates the directory if needed. Returns
+/// `None` if there is nowhere to keep it.
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local").join("share"),
+    };
+    let dir = base.join("rust-mines");
+    fs::create_dir_all(&dir).ok()?;
+    Some(dir.join(name))
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use rustbox::{
+    Color,
+    EventResult,
+    RustBox,
+    Style,
+};
+
+/// The subset of the rustbox API the game draws and polls through, so the
+/// screen can be swapped out for a headless one.
+pub trait Terminal {
+    fn clear(&self);
+    fn present(&self);
+    fn set_cursor(&self, x: isize, y: isize);
+    fn print(&self, x: usize, y: usize, style: Style, fg: Color, bg: Color, s: &str);
+    fn print_char(&self, x: usize, y: usize, style: Style, fg: Color, bg: Color, ch: char);
+    fn poll_event(&self, raw: bool) -> EventResult;
+    fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult;
+}
+
+impl Terminal for RustBox {
+    fn clear(&self) {
+        RustBox::clear(self)
+    }
+
+    fn present(&self) {
+        RustBox::present(self)
+    }
+
+    fn set_cursor(&self, x: isize, y: isize) {
+        RustBox::set_cursor(self, x, y)
+    }
+
+    fn print(&self, x: usize, y: usize, style: Style, fg: Color, bg: Color, s: &str) {
+        RustBox::print(self, x, y, style, fg, bg, s)
+    }
+
+    fn print_char(&self, x: usize, y: usize, style: Style, fg: Color, bg: Color, ch: char) {
+        RustBox::print_char(self, x, y, style, fg, bg, ch)
+    }
+
+    fn poll_event(&self, raw: bool) -> EventResult {
+        RustBox::poll_event(self, raw)
+    }
+
+    fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult {
+        RustBox::peek_event(self, timeout, raw)
+    }
+}